@@ -0,0 +1,147 @@
+extern crate rltk;
+extern crate specs;
+use super::{EventQueue, GameEvent, GameLog, GameStats, LogBuilder};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Milestones reached during a run, kept in `GameStats` by name.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Achievement {
+    FirstBlood,
+    Slayer,
+    BottomsUp,
+    Hoarder,
+}
+
+/// How many kills earn `Slayer`.
+const SLAYER_KILLS: usize = 10;
+
+/// How many items picked up earn `Hoarder`.
+const HOARDER_ITEMS: i32 = 5;
+
+impl Achievement {
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "First Blood",
+            Achievement::Slayer => "Slayer",
+            Achievement::BottomsUp => "Bottoms Up",
+            Achievement::Hoarder => "Hoarder",
+        }
+    }
+}
+
+/// Watches the events for milestones and announces each one the first time
+/// it is reached. Runs after `StatisticsSystem`, so the totals it checks
+/// already include this turn's events.
+pub struct AchievementSystem {}
+
+impl<'a> System<'a> for AchievementSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, EventQueue>,
+        WriteExpect<'a, GameStats>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, queue, mut game_stats, mut log) = data;
+
+        for event in queue.events.iter() {
+            let earned = match *event {
+                GameEvent::Died { entity } if entity != *player_entity => {
+                    if game_stats.kills.len() >= SLAYER_KILLS {
+                        Some(Achievement::Slayer)
+                    } else {
+                        Some(Achievement::FirstBlood)
+                    }
+                }
+                GameEvent::ItemPickedUp { collector, .. } if collector == *player_entity => {
+                    if game_stats.items_collected >= HOARDER_ITEMS {
+                        Some(Achievement::Hoarder)
+                    } else {
+                        None
+                    }
+                }
+                GameEvent::Healed { entity, .. } if entity == *player_entity => {
+                    Some(Achievement::BottomsUp)
+                }
+                _ => None,
+            };
+
+            if let Some(achievement) = earned {
+                let name = achievement.name().to_string();
+                if !game_stats.achievements.contains(&name) {
+                    LogBuilder::new()
+                        .coloured("Achievement unlocked: ", RGB::named(rltk::GOLD))
+                        .append(&name)
+                        .log(&mut log);
+                    game_stats.achievements.push(name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_world() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        let player = world.create_entity().build();
+        let monster = world.create_entity().build();
+        world.insert(player);
+        world.insert(EventQueue::default());
+        world.insert(GameLog::new(Vec::new()));
+        world.insert(GameStats::default());
+        (world, player, monster)
+    }
+
+    fn publish(world: &mut World, events: Vec<GameEvent>) {
+        world.fetch_mut::<EventQueue>().events = events;
+        AchievementSystem {}.run_now(world);
+    }
+
+    #[test]
+    fn achievements_unlock_once() {
+        let (mut world, player, monster) = test_world();
+        publish(
+            &mut world,
+            vec![
+                GameEvent::Died { entity: monster },
+                GameEvent::Died { entity: monster },
+                GameEvent::Died { entity: player },
+            ],
+        );
+
+        assert_eq!(world.fetch::<GameStats>().achievements, vec!["First Blood"]);
+        let log = world.fetch::<GameLog>();
+        assert_eq!(log.entries.len(), 1);
+        assert_eq!(log.entries[0].text(), "Achievement unlocked: First Blood");
+    }
+
+    #[test]
+    fn achievements_check_the_totals() {
+        let (mut world, player, monster) = test_world();
+        world.fetch_mut::<GameStats>().items_collected = HOARDER_ITEMS;
+        publish(
+            &mut world,
+            vec![
+                GameEvent::ItemPickedUp {
+                    collector: player,
+                    item: monster,
+                },
+                GameEvent::Healed {
+                    entity: player,
+                    item: monster,
+                    amount: 5,
+                },
+            ],
+        );
+
+        assert_eq!(
+            world.fetch::<GameStats>().achievements,
+            vec!["Hoarder", "Bottoms Up"]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+extern crate rltk;
+use super::{Map, Point, Position, Renderable, TileType};
+use rltk::{Console, Rltk, RGB};
+use specs::prelude::*;
+
+/// The size of the area of the screen the map is drawn in, below which is the UI.
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// The part of the map that is on screen, given by the map position shown in
+/// the top left corner. Used to translate between map and screen positions.
+#[derive(Clone, Copy)]
+pub struct Viewport {
+    pub min_x: i32,
+    pub min_y: i32,
+}
+
+impl Viewport {
+    /// Centres the view on a point, stopping at the edges of maps larger than
+    /// the screen so that nothing beyond them is shown.
+    pub fn centred_on(map: &Map, centre: Point) -> Viewport {
+        Viewport {
+            min_x: view_start(centre.x, map.width, VIEW_WIDTH),
+            min_y: view_start(centre.y, map.height, VIEW_HEIGHT),
+        }
+    }
+
+    /// The view following the player.
+    pub fn for_player(ecs: &World) -> Viewport {
+        Viewport::centred_on(&ecs.fetch::<Map>(), *ecs.fetch::<Point>())
+    }
+
+    /// The screen position of a map position, if it is on screen.
+    pub fn world_to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.min_x, y - self.min_y);
+        if is_on_view(screen_x, screen_y) {
+            Some((screen_x, screen_y))
+        } else {
+            None
+        }
+    }
+
+    /// The map position shown at a screen position, if it is in the map view.
+    pub fn screen_to_world(&self, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if is_on_view(screen_x, screen_y) {
+            Some((screen_x + self.min_x, screen_y + self.min_y))
+        } else {
+            None
+        }
+    }
+}
+
+fn is_on_view(screen_x: i32, screen_y: i32) -> bool {
+    (0..VIEW_WIDTH).contains(&screen_x) && (0..VIEW_HEIGHT).contains(&screen_y)
+}
+
+/// Where the view starts along one axis, keeping `centre` in the middle.
+fn view_start(centre: i32, map_size: i32, view_size: i32) -> i32 {
+    if map_size <= view_size {
+        0
+    } else {
+        (centre - view_size / 2).clamp(0, map_size - view_size)
+    }
+}
+
+/// Draws the part of the map around the player, and everything on it the
+/// player can see.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let viewport = Viewport::for_player(ecs);
+    render_map(&map, &viewport, ctx);
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    for (pos, render) in (&positions, &renderables).join() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if !map.visible_tiles[idx] {
+            continue;
+        }
+        if let Some((x, y)) = viewport.world_to_screen(pos.x, pos.y) {
+            ctx.set(x, y, render.fg, render.bg, render.glyph);
+        }
+    }
+}
+
+/// Draws the revealed tiles of a map that fall within the view. Tiles that
+/// aren't currently visible are greyed out.
+pub fn render_map(map: &Map, viewport: &Viewport, ctx: &mut Rltk) {
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let (x, y) = (screen_x + viewport.min_x, screen_y + viewport.min_y);
+            if x >= map.width || y >= map.height {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if !map.revealed_tiles[idx] {
+                continue;
+            }
+
+            let (glyph, mut fg) = tile_glyph(map.tiles[idx]);
+            if !map.visible_tiles[idx] {
+                fg = fg.to_greyscale()
+            }
+            ctx.set(screen_x, screen_y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
+        }
+    }
+}
+
+/// The character and colour a tile is drawn with.
+pub fn tile_glyph(tile: TileType) -> (u8, RGB) {
+    match tile {
+        TileType::FLOOR => (rltk::to_cp437('.'), RGB::from_f32(0.0, 0.5, 0.5)),
+        TileType::WALL => (rltk::to_cp437('#'), RGB::from_f32(0.0, 1.0, 0.0)),
+        TileType::DOWNSTAIRS => (rltk::to_cp437('>'), RGB::from_f32(0.0, 1.0, 1.0)),
+        TileType::UPSTAIRS => (rltk::to_cp437('<'), RGB::from_f32(0.0, 1.0, 1.0)),
+        TileType::RUBBLE => (rltk::to_cp437(';'), RGB::from_f32(0.6, 0.5, 0.4)),
+        TileType::SHALLOWWATER => (rltk::to_cp437('~'), RGB::from_f32(0.3, 0.5, 1.0)),
+    }
+}
@@ -1,53 +1,170 @@
 use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
 use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
 
-#[derive(Component)]
-pub struct WantsToDrinkPotion {
-    pub potion: Entity,
+/// Uses an item, aimed at a point on the map, or at the user if there is no
+/// target.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUseItem {
+    pub item: Entity,
+    pub target: Option<rltk::Point>,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToDropItem {
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InBackpack {
     pub owner: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
-pub struct Potion {
+/// An item that is aimed at a point up to `range` tiles away when it is used.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ranged {
+    pub range: i32,
+}
+
+/// An item that is used up when it is used.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Consumable {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesHealing {
     pub heal_amount: i32,
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsDamage {
+    pub damage: i32,
+}
+
+/// Spreads an item's effects to everything within `radius` tiles of the
+/// target.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AreaOfEffect {
+    pub radius: i32,
+}
+
+/// Confuses its targets for a number of turns.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Confusion {
+    pub turns: i32,
+}
+
+/// A monster that has been confused, and loses its turns until this wears off.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Confused {
+    pub turns: i32,
+}
+
+/// Where an item is worn or wielded. Only one item fits in each slot.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+    Armour,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+/// An item that is being worn or wielded. Equipped items stay in their
+/// owner's backpack.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+/// Added to the power of whoever has this item equipped.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+/// Added to the defence of whoever has this item equipped.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DefenceBonus {
+    pub defence: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToEquip {
+    pub item: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUnequip {
+    pub item: Entity,
+}
+
 /// Examining Cursor, used to point to things on the screen.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Cursor {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component, Debug, Clone)]
+/// The seed that the current run's random number generator was created from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RunSeed {
+    pub seed: u64,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToMelee {
     pub target: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SufferDamage {
     pub amount: i32,
 }
 
-#[derive(Component)]
+/// How quickly an entity acts. Each tick of game time adds `speed` to
+/// `energy`, and each action spends some of it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Energy {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+/// What a monster is doing. See `MonsterAI` for how monsters move between
+/// these.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState {
+    /// Stays put until the player comes close or it is hurt.
+    Sleeping,
+    Wandering,
+    Chasing,
+    /// Heading to where the player was last seen.
+    Searching,
+    Fleeing,
+}
+
+/// A monster's current behaviour, and where it last saw the player.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Behaviour {
+    pub state: AiState,
+    pub last_seen: Option<Position>,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub hp: i32,
@@ -55,37 +172,51 @@ pub struct CombatStats {
     pub power: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
 /// This component indicates that the entity is solid, and cannot be walked through.
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
     pub glyph: u8,
     pub fg: RGB,
     pub bg: RGB,
 }
+
+/// Marker for entities that should be written to the save file.
+pub struct SerializeMe;
+
+/// Temporary entity used to carry resources through the save file.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: super::map::Map,
+    pub log: super::gamelog::GameLog,
+    pub cursor: Cursor,
+    pub seed: RunSeed,
+    pub rng: super::rng::RandomNumberGenerator,
+    pub stats: super::gamestats::GameStats,
+}
@@ -1,21 +1,31 @@
 extern crate specs;
-use super::{CombatStats, GameLog, Name, Player, SufferDamage};
-use rltk::console;
+use super::{CombatStats, EventQueue, GameEvent, Player, RunState, SufferDamage};
 use specs::prelude::*;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, EventQueue>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut events, mut stats, mut damage) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            let was_alive = stats.hp > 0;
             stats.hp -= damage.amount;
+
+            events.emit(GameEvent::Damaged {
+                target: entity,
+                amount: damage.amount,
+            });
+            if was_alive && stats.hp < 1 {
+                events.emit(GameEvent::Died { entity });
+            }
         }
 
         damage.clear();
@@ -26,24 +36,29 @@ impl DamageSystem {
     pub fn delete_the_dead(ecs: &mut World) {
         let mut dead: Vec<Entity> = Vec::new();
         {
-            let mut log = ecs.fetch_mut::<GameLog>();
+            let mut runstate = ecs.fetch_mut::<RunState>();
             let players = ecs.read_storage::<Player>();
             let combat_stats = ecs.read_storage::<CombatStats>();
-            let names = ecs.read_storage::<Name>();
             let entities = ecs.entities();
 
             for (entity, stats) in (&entities, &combat_stats).join() {
                 if stats.hp < 1 {
-                    let player = players.get(entity);
-                    match player {
-                        None => {
-                            let victim_name = names.get(entity);
-                            if let Some(victim_name) = victim_name {
-                                log.entries.push(format!("{} is dead", &victim_name.name));
+                    match players.get(entity) {
+                        None => dead.push(entity),
+                        // The player isn't deleted, so that the game over
+                        // screen can still report on them. Their death only
+                        // ends the game while it is being played, not once
+                        // the menus have taken over.
+                        Some(_) => {
+                            if matches!(
+                                *runstate,
+                                RunState::AwaitingInput
+                                    | RunState::PlayerTurn
+                                    | RunState::MonsterTurn
+                            ) {
+                                *runstate = RunState::GameOver;
                             }
-                            dead.push(entity)
                         }
-                        Some(_) => console::log("You are dead"),
                     }
                 }
             }
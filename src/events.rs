@@ -0,0 +1,352 @@
+extern crate rltk;
+extern crate specs;
+use super::{GameLog, GameStats, LogBuilder, Name, Renderable};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Something that happened during a turn. Systems emit these instead of
+/// writing to the log or statistics themselves, and subscriber systems react
+/// to them once every system has had its turn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A melee attack landed, or failed to hurt the target if `damage` is 0.
+    Attacked {
+        attacker: Entity,
+        target: Entity,
+        damage: i32,
+    },
+    Damaged {
+        target: Entity,
+        amount: i32,
+    },
+    Died {
+        entity: Entity,
+    },
+    ItemPickedUp {
+        collector: Entity,
+        item: Entity,
+    },
+    ItemDropped {
+        dropper: Entity,
+        item: Entity,
+    },
+    Healed {
+        entity: Entity,
+        item: Entity,
+        amount: i32,
+    },
+    /// An item, such as a scroll, damaged something.
+    ItemHit {
+        user: Entity,
+        item: Entity,
+        target: Entity,
+        damage: i32,
+    },
+    Confused {
+        item: Entity,
+        target: Entity,
+    },
+    ItemEquipped {
+        owner: Entity,
+        item: Entity,
+    },
+    ItemUnequipped {
+        owner: Entity,
+        item: Entity,
+    },
+}
+
+/// The events emitted since the systems were last run.
+#[derive(Default)]
+pub struct EventQueue {
+    pub events: Vec<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// Describes events in the game log.
+pub struct EventLogSystem {}
+
+impl<'a> System<'a> for EventLogSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, EventQueue>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, queue, mut log, names, renderables) = data;
+
+        // Describes an entity by its name, in the colour it is drawn with
+        let named = |builder: LogBuilder, entity: Entity| match names.get(entity) {
+            Some(name) => builder.named(name, renderables.get(entity)),
+            None => builder.append("something"),
+        };
+
+        for event in queue.events.iter() {
+            match *event {
+                GameEvent::Attacked {
+                    attacker,
+                    target,
+                    damage,
+                } => {
+                    let builder = named(LogBuilder::new(), attacker);
+                    if damage == 0 {
+                        named(builder.append(" is unable to hurt "), target).log(&mut log);
+                    } else {
+                        named(builder.append(" hits "), target)
+                            .append(" for ")
+                            .damage(damage)
+                            .append(" damage")
+                            .log(&mut log);
+                    }
+                }
+                GameEvent::Died { entity } => {
+                    if entity == *player_entity {
+                        LogBuilder::new()
+                            .coloured("You are dead", RGB::named(rltk::RED))
+                            .log(&mut log);
+                    } else if names.get(entity).is_some() {
+                        named(LogBuilder::new(), entity)
+                            .append(" is dead")
+                            .log(&mut log);
+                    }
+                }
+                GameEvent::ItemPickedUp { collector, item } if collector == *player_entity => {
+                    named(LogBuilder::new().append("You pick up the "), item)
+                        .append(".")
+                        .log(&mut log);
+                }
+                GameEvent::ItemDropped { dropper, item } if dropper == *player_entity => {
+                    named(LogBuilder::new().append("You drop the "), item)
+                        .append(".")
+                        .log(&mut log);
+                }
+                GameEvent::Healed {
+                    entity,
+                    item,
+                    amount,
+                } if entity == *player_entity => {
+                    named(LogBuilder::new().append("The "), item)
+                        .append(" heals you for ")
+                        .healing(amount)
+                        .append(" hp.")
+                        .log(&mut log);
+                }
+                GameEvent::ItemHit {
+                    item,
+                    target,
+                    damage,
+                    ..
+                } => {
+                    let builder = named(LogBuilder::new().append("The "), item);
+                    named(builder.append(" hits "), target)
+                        .append(" for ")
+                        .damage(damage)
+                        .append(" damage")
+                        .log(&mut log);
+                }
+                GameEvent::Confused { target, .. } => {
+                    named(LogBuilder::new(), target)
+                        .append(" is confused.")
+                        .log(&mut log);
+                }
+                GameEvent::ItemEquipped { owner, item } if owner == *player_entity => {
+                    named(LogBuilder::new().append("You equip the "), item)
+                        .append(".")
+                        .log(&mut log);
+                }
+                GameEvent::ItemUnequipped { owner, item } if owner == *player_entity => {
+                    named(LogBuilder::new().append("You take off the "), item)
+                        .append(".")
+                        .log(&mut log);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Keeps the running totals in `GameStats` up to date.
+pub struct StatisticsSystem {}
+
+impl<'a> System<'a> for StatisticsSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, EventQueue>,
+        WriteExpect<'a, GameStats>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, queue, mut game_stats, names) = data;
+
+        for event in queue.events.iter() {
+            match *event {
+                GameEvent::Attacked {
+                    attacker,
+                    target,
+                    damage,
+                } if damage > 0 => {
+                    if attacker == *player_entity {
+                        game_stats.damage_dealt += damage;
+                    }
+                    if target == *player_entity {
+                        game_stats.damage_taken += damage;
+                        game_stats.last_hit_by = names.get(attacker).map(|n| n.name.to_string());
+                    }
+                }
+                GameEvent::ItemHit {
+                    user,
+                    item,
+                    target,
+                    damage,
+                } => {
+                    if user == *player_entity {
+                        game_stats.damage_dealt += damage;
+                    }
+                    if target == *player_entity {
+                        game_stats.damage_taken += damage;
+                        game_stats.last_hit_by = names.get(item).map(|n| n.name.to_string());
+                    }
+                }
+                GameEvent::Died { entity } if entity != *player_entity => {
+                    if let Some(name) = names.get(entity) {
+                        game_stats.kills.push(name.name.to_string());
+                    }
+                }
+                GameEvent::ItemPickedUp { collector, .. } if collector == *player_entity => {
+                    game_stats.items_collected += 1;
+                }
+                GameEvent::Healed { entity, .. } if entity == *player_entity => {
+                    game_stats.potions_used += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world holding just what the subscribers read, with a named player
+    /// and goblin to publish events about.
+    fn test_world() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.register::<Name>();
+        world.register::<Renderable>();
+        let player = world
+            .create_entity()
+            .with(Name {
+                name: "Player".to_string(),
+            })
+            .build();
+        let goblin = world
+            .create_entity()
+            .with(Name {
+                name: "Goblin".to_string(),
+            })
+            .build();
+        world.insert(player);
+        world.insert(EventQueue::default());
+        world.insert(GameLog::new(Vec::new()));
+        world.insert(GameStats::default());
+        (world, player, goblin)
+    }
+
+    fn publish(world: &mut World, events: Vec<GameEvent>) {
+        world.fetch_mut::<EventQueue>().events = events;
+        EventLogSystem {}.run_now(world);
+        StatisticsSystem {}.run_now(world);
+    }
+
+    fn log_text(world: &World) -> Vec<String> {
+        world
+            .fetch::<GameLog>()
+            .entries
+            .iter()
+            .map(|entry| entry.text())
+            .collect()
+    }
+
+    #[test]
+    fn log_describes_events() {
+        let (mut world, player, goblin) = test_world();
+        publish(
+            &mut world,
+            vec![
+                GameEvent::Attacked {
+                    attacker: player,
+                    target: goblin,
+                    damage: 3,
+                },
+                GameEvent::Attacked {
+                    attacker: goblin,
+                    target: player,
+                    damage: 0,
+                },
+                GameEvent::Died { entity: goblin },
+                GameEvent::Died { entity: player },
+            ],
+        );
+
+        assert_eq!(
+            log_text(&world),
+            vec![
+                "Player hits Goblin for 3 damage",
+                "Goblin is unable to hurt Player",
+                "Goblin is dead",
+                "You are dead",
+            ]
+        );
+    }
+
+    #[test]
+    fn statistics_total_events() {
+        let (mut world, player, goblin) = test_world();
+        publish(
+            &mut world,
+            vec![
+                GameEvent::Attacked {
+                    attacker: player,
+                    target: goblin,
+                    damage: 3,
+                },
+                GameEvent::Attacked {
+                    attacker: goblin,
+                    target: player,
+                    damage: 2,
+                },
+                GameEvent::ItemPickedUp {
+                    collector: player,
+                    item: goblin,
+                },
+                GameEvent::ItemPickedUp {
+                    collector: goblin,
+                    item: player,
+                },
+                GameEvent::Died { entity: goblin },
+            ],
+        );
+
+        let stats = world.fetch::<GameStats>();
+        assert_eq!(stats.damage_dealt, 3);
+        assert_eq!(stats.damage_taken, 2);
+        assert_eq!(stats.last_hit_by.as_deref(), Some("Goblin"));
+        assert_eq!(stats.items_collected, 1);
+        assert_eq!(stats.kills, vec!["Goblin"]);
+    }
+}
@@ -0,0 +1,360 @@
+extern crate specs;
+use super::{GameStats, Map, Point, TileType};
+use specs::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// The value of tiles that can't reach any goal.
+pub const UNREACHABLE: f32 = f32::MAX;
+
+/// The extra cost of stepping through a tile something is standing on, so
+/// that monsters spread out and go around each other when there is room.
+const OCCUPIED_COST: f32 = 4.0;
+
+/// How strongly fleeing monsters prefer getting far away over getting away
+/// quickly. Values below -1 make them slip past the player into open areas
+/// rather than run into dead ends.
+const FLEE_FACTOR: f32 = -1.2;
+
+/// A tile waiting to be expanded by a field's search, ordered so that the
+/// cheapest is popped first from a `BinaryHeap`.
+struct Frontier {
+    cost: f32,
+    idx: usize,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.idx.cmp(&self.idx))
+    }
+}
+
+/// The cost of reaching the nearest goal from every tile on the map, lowest at
+/// the goals themselves. Anything heading for those goals can step to its
+/// lowest valued neighbour, so one field serves any number of monsters.
+/// rltk's A* isn't used for pathing as it expands the same tiles over and
+/// over on open ground, so it slows down sharply in caves and gives up
+/// without a path on open areas of a few hundred tiles.
+#[derive(Clone, Default)]
+pub struct FlowField {
+    pub values: Vec<f32>,
+}
+
+/// The flow fields shared by every monster, recomputed by `FlowFieldSystem`
+/// at most once a turn.
+#[derive(Default)]
+pub struct FlowFields {
+    /// Leads towards the player.
+    pub to_player: FlowField,
+    /// Leads away from the player.
+    pub from_player: FlowField,
+    /// Leads to the tiles where searching monsters last saw the player,
+    /// each worked out the first time it is needed in a turn.
+    to_last_seen: HashMap<usize, FlowField>,
+    /// The depth, turn and player tile the fields were last computed for.
+    computed_for: Option<(i32, i32, usize)>,
+}
+
+impl FlowField {
+    /// A field leading to the nearest of the given tiles.
+    pub fn new(map: &Map, goals: &[usize]) -> FlowField {
+        FlowField::from_seeds(map, goals.iter().map(|idx| (*idx, 0.0)).collect())
+    }
+
+    /// A field leading away from the goals of another field.
+    pub fn fleeing(map: &Map, field: &FlowField) -> FlowField {
+        let seeds = field
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != UNREACHABLE)
+            .map(|(idx, value)| (idx, value * FLEE_FACTOR))
+            .collect();
+        FlowField::from_seeds(map, seeds)
+    }
+
+    /// Runs Dijkstra's algorithm outwards from tiles with a starting value.
+    fn from_seeds(map: &Map, seeds: Vec<(usize, f32)>) -> FlowField {
+        let mut values = vec![UNREACHABLE; map.tiles.len()];
+        let mut open_list = BinaryHeap::new();
+        for (idx, cost) in seeds {
+            if cost < values[idx] {
+                values[idx] = cost;
+                open_list.push(Frontier { cost, idx });
+            }
+        }
+
+        while let Some(Frontier { cost, idx }) = open_list.pop() {
+            // Skip tiles that were reached more cheaply since being queued
+            if cost > values[idx] {
+                continue;
+            }
+
+            if map.tiles[idx] == TileType::WALL {
+                continue;
+            }
+
+            for neighbour in neighbours(map, idx) {
+                if map.tiles[neighbour] == TileType::WALL {
+                    continue;
+                }
+                // The neighbour is as far from a goal as this tile, plus
+                // what it costs to step from the neighbour onto this tile
+                let new_cost = cost + step_cost(map, neighbour, idx);
+                if new_cost < values[neighbour] {
+                    values[neighbour] = new_cost;
+                    open_list.push(Frontier {
+                        cost: new_cost,
+                        idx: neighbour,
+                    });
+                }
+            }
+        }
+
+        FlowField { values }
+    }
+
+    /// The candidate that is cheapest to reach a goal through, counting the
+    /// step onto it, out of those with a lower value than where we stand.
+    /// Returns none if no candidate improves on it.
+    pub fn best_step(&self, map: &Map, from_idx: usize, candidates: &[usize]) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for idx in candidates.iter() {
+            let value = self.values[*idx];
+            if value >= self.values[from_idx] {
+                continue;
+            }
+
+            let total = step_cost(map, from_idx, *idx) + value;
+            if best.is_none_or(|(_, best_total)| total < best_total) {
+                best = Some((*idx, total));
+            }
+        }
+        best.map(|(idx, _)| idx)
+    }
+}
+
+impl FlowFields {
+    /// The field leading to a tile where the player was last seen, shared by
+    /// every monster searching there this turn.
+    pub fn towards_last_seen(&mut self, map: &Map, idx: usize) -> &FlowField {
+        self.to_last_seen
+            .entry(idx)
+            .or_insert_with(|| FlowField::new(map, &[idx]))
+    }
+}
+
+/// The tiles around a tile, in all eight directions, that are on the map.
+fn neighbours(map: &Map, idx: usize) -> Vec<usize> {
+    let (x, y) = map.idx_to_xy(idx);
+    let mut neighbours = Vec::new();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if (dx != 0 || dy != 0) && map.is_in_bounds(x + dx, y + dy) {
+                neighbours.push(map.xy_idx(x + dx, y + dy));
+            }
+        }
+    }
+    neighbours
+}
+
+/// What it costs to step from a tile onto a neighbouring one, the same as
+/// for any other path plus a little more if something is standing there.
+fn step_cost(map: &Map, from_idx: usize, to_idx: usize) -> f32 {
+    if map.blocked[to_idx] {
+        map.step_cost(from_idx, to_idx) + OCCUPIED_COST
+    } else {
+        map.step_cost(from_idx, to_idx)
+    }
+}
+
+/// Recomputes the shared flow fields whenever the turn, the level or the
+/// player's position has changed since they were last worked out.
+pub struct FlowFieldSystem {}
+
+impl<'a> System<'a> for FlowFieldSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, GameStats>,
+        WriteExpect<'a, FlowFields>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, game_stats, mut fields) = data;
+
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        let key = (map.depth, game_stats.turns, player_idx);
+        if fields.computed_for == Some(key) {
+            return;
+        }
+
+        let to_player = FlowField::new(&map, &[player_idx]);
+        fields.from_player = FlowField::fleeing(&map, &to_player);
+        fields.to_player = to_player;
+        fields.to_last_seen.clear();
+        fields.computed_for = Some(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::BaseMap;
+
+    /// A 10x10 map of floor surrounded by wall.
+    fn open_map() -> Map {
+        let mut map = Map::new(1, 10, 10);
+        for y in 1..9 {
+            for x in 1..9 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::FLOOR;
+            }
+        }
+        map.populate_blocked();
+        map
+    }
+
+    fn set_tile(map: &mut Map, x: i32, y: i32, tile: TileType) {
+        let idx = map.xy_idx(x, y);
+        map.tiles[idx] = tile;
+    }
+
+    /// An open map with a patch of rubble and a wall to go around.
+    fn test_map() -> Map {
+        let mut map = open_map();
+        for (x, y) in [(3, 3), (4, 3), (3, 4), (4, 4)] {
+            set_tile(&mut map, x, y, TileType::RUBBLE);
+        }
+        for y in 2..8 {
+            set_tile(&mut map, 6, y, TileType::WALL);
+        }
+        map.populate_blocked();
+        map
+    }
+
+    /// Follows the field from a tile to its goal, a step at a time, the way
+    /// the player moves: only onto open neighbouring tiles.
+    fn walk(map: &Map, field: &FlowField, start: usize) -> Option<Vec<usize>> {
+        let mut steps = vec![start];
+        let mut current = start;
+        while field.values[current] > 0.0 {
+            let candidates: Vec<usize> = map
+                .get_available_exits(current)
+                .iter()
+                .map(|(idx, _)| *idx)
+                .collect();
+            current = field.best_step(map, current, &candidates)?;
+            steps.push(current);
+        }
+        Some(steps)
+    }
+
+    /// What it costs to walk a path, one step at a time.
+    fn path_cost(map: &Map, steps: &[usize]) -> f32 {
+        steps
+            .windows(2)
+            .map(|step| map.step_cost(step[0], step[1]))
+            .sum()
+    }
+
+    #[test]
+    fn walking_the_field_costs_its_value() {
+        let map = test_map();
+        let goal = map.xy_idx(8, 5);
+        let field = FlowField::new(&map, &[goal]);
+
+        for idx in 0..map.tiles.len() {
+            if map.tiles[idx] == TileType::WALL {
+                assert_eq!(field.values[idx], UNREACHABLE);
+                continue;
+            }
+            let path = walk(&map, &field, idx).expect("No path to the goal");
+            assert_eq!(path.last(), Some(&goal));
+            let cost = path_cost(&map, &path);
+            assert!(
+                (field.values[idx] - cost).abs() < 0.001,
+                "Tile {} costs {} in the field but {} to walk",
+                idx,
+                field.values[idx],
+                cost
+            );
+        }
+    }
+
+    #[test]
+    fn fields_lead_around_walls() {
+        let mut map = open_map();
+        for y in 1..8 {
+            set_tile(&mut map, 5, y, TileType::WALL);
+        }
+        map.populate_blocked();
+
+        let goal = map.xy_idx(8, 2);
+        let field = FlowField::new(&map, &[goal]);
+        let path = walk(&map, &field, map.xy_idx(2, 2)).expect("No path around the wall");
+
+        assert!(path.iter().all(|idx| map.tiles[*idx] != TileType::WALL));
+        assert!(path.contains(&map.xy_idx(5, 8)));
+    }
+
+    #[test]
+    fn fields_lead_diagonally() {
+        let map = open_map();
+        let field = FlowField::new(&map, &[map.xy_idx(6, 6)]);
+        let path = walk(&map, &field, map.xy_idx(1, 1)).expect("No path across open floor");
+
+        assert_eq!(path.len(), 6);
+    }
+
+    #[test]
+    fn fields_avoid_rough_ground_when_going_around_is_cheaper() {
+        let mut map = open_map();
+        for y in 3..9 {
+            set_tile(&mut map, 5, y, TileType::RUBBLE);
+        }
+        let (start, goal) = (map.xy_idx(4, 3), map.xy_idx(6, 3));
+
+        let field = FlowField::new(&map, &[goal]);
+        let path = walk(&map, &field, start).expect("No path over the rubble");
+        assert!(path.contains(&map.xy_idx(5, 2)));
+
+        // With no way around, the path crosses the rubble
+        set_tile(&mut map, 5, 2, TileType::WALL);
+        map.populate_blocked();
+        let field = FlowField::new(&map, &[goal]);
+        let path = walk(&map, &field, start).expect("No path over the rubble");
+        assert_eq!(path, vec![start, map.xy_idx(5, 3), goal]);
+    }
+
+    #[test]
+    fn walled_off_tiles_are_unreachable() {
+        let mut map = open_map();
+        for y in 1..9 {
+            set_tile(&mut map, 5, y, TileType::WALL);
+        }
+        map.populate_blocked();
+
+        let field = FlowField::new(&map, &[map.xy_idx(8, 2)]);
+        let start = map.xy_idx(2, 2);
+        assert_eq!(field.values[start], UNREACHABLE);
+        assert!(walk(&map, &field, start).is_none());
+    }
+}
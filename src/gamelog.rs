@@ -1,3 +1,122 @@
+extern crate rltk;
+use super::{Name, Renderable};
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The most messages kept in the log. Once full, the oldest message is
+/// dropped for each new one, so long runs don't grow the log forever.
+pub const MAX_LOG_ENTRIES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GameLog {
-    pub entries: Vec<String>,
+    pub entries: VecDeque<LogEntry>,
+}
+
+/// A piece of a log message drawn in a single colour.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogFragment {
+    pub colour: RGB,
+    pub text: String,
+}
+
+/// A single message, made up of coloured fragments.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+    pub fragments: Vec<LogFragment>,
+}
+
+/// Builds a coloured log message a piece at a time, for example
+/// `LogBuilder::new().named(name, renderable).append(" is dead").log(&mut log)`.
+#[derive(Default)]
+pub struct LogBuilder {
+    fragments: Vec<LogFragment>,
+}
+
+impl GameLog {
+    pub fn new(entries: Vec<String>) -> GameLog {
+        let mut log = GameLog {
+            entries: VecDeque::new(),
+        };
+        for entry in entries {
+            log.push(entry);
+        }
+        log
+    }
+
+    /// Adds a message to the end of the log, dropping the oldest if full.
+    /// Plain strings are logged in white.
+    pub fn push<E: Into<LogEntry>>(&mut self, entry: E) {
+        if self.entries.len() >= MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry.into());
+    }
+}
+
+impl LogEntry {
+    /// The message without its colours.
+    pub fn text(&self) -> String {
+        self.fragments.iter().map(|f| f.text.as_str()).collect()
+    }
+}
+
+impl From<String> for LogEntry {
+    fn from(text: String) -> LogEntry {
+        LogBuilder::new().append(text).build()
+    }
+}
+
+impl From<&str> for LogEntry {
+    fn from(text: &str) -> LogEntry {
+        LogBuilder::new().append(text).build()
+    }
+}
+
+impl LogBuilder {
+    pub fn new() -> LogBuilder {
+        LogBuilder {
+            fragments: Vec::new(),
+        }
+    }
+
+    /// Adds plain white text.
+    pub fn append<T: ToString>(self, text: T) -> LogBuilder {
+        self.coloured(text, RGB::named(rltk::WHITE))
+    }
+
+    pub fn coloured<T: ToString>(mut self, text: T, colour: RGB) -> LogBuilder {
+        self.fragments.push(LogFragment {
+            colour,
+            text: text.to_string(),
+        });
+        self
+    }
+
+    /// Adds an entity's name in the colour it is drawn with on the map.
+    pub fn named(self, name: &Name, renderable: Option<&Renderable>) -> LogBuilder {
+        let colour = renderable.map_or(RGB::named(rltk::WHITE), |r| r.fg);
+        self.coloured(&name.name, colour)
+    }
+
+    /// Adds an amount of damage, in red.
+    pub fn damage(self, amount: i32) -> LogBuilder {
+        self.coloured(amount, RGB::named(rltk::RED))
+    }
+
+    /// Adds an amount of healing, in green.
+    pub fn healing(self, amount: i32) -> LogBuilder {
+        self.coloured(amount, RGB::named(rltk::GREEN))
+    }
+
+    pub fn build(self) -> LogEntry {
+        LogEntry {
+            fragments: self.fragments,
+        }
+    }
+
+    /// Finishes the message and adds it to the log.
+    pub fn log(self, log: &mut GameLog) {
+        log.push(self.build());
+    }
 }
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Running totals for the current run, used for balance reports.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GameStats {
+    pub turns: i32,
+    /// Game time, with `scheduler::TICKS_PER_TURN` ticks to a turn.
+    pub ticks: i32,
+    pub kills: Vec<String>,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub potions_used: i32,
+    pub items_collected: i32,
+    /// Whatever last hurt the player, reported as the cause of death.
+    pub last_hit_by: Option<String>,
+    /// The names of the achievements earned so far.
+    pub achievements: Vec<String>,
+}
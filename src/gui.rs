@@ -1,9 +1,64 @@
 extern crate rltk;
 extern crate specs;
-use super::{CombatStats, Cursor, GameLog, InBackpack, Name, Player, Position, RunState, State};
+use super::{
+    saveload_system, targetable_tiles, CombatStats, Cursor, Equipped, GameLog, GameStats,
+    HighScoreTable, InBackpack, LogEntry, LogFragment, Map, MapGenConfig, Name, Player, Point,
+    Position, RunState, State, Viewport, VIEW_WIDTH,
+};
 use rltk::{Console, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
 
+/// The widest a message can be drawn inside the log box before it is wrapped.
+const LOG_WIDTH: usize = 76;
+
+#[derive(PartialEq)]
+pub enum GameOverResult {
+    NoSelection,
+    NewGame,
+    MainMenu,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum MainMenuSelection {
+    NewGame,
+    Continue,
+    HighScores,
+    Options,
+    Quit,
+}
+
+#[derive(PartialEq)]
+pub enum MainMenuResult {
+    NoSelection { selected: MainMenuSelection },
+    Selected { selected: MainMenuSelection },
+}
+
+#[derive(PartialEq)]
+pub enum MessageHistoryResult {
+    NoResponse { offset: usize },
+    Close,
+}
+
+#[derive(PartialEq)]
+pub enum HighScoresResult {
+    NoResponse,
+    Back,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum OptionsSelection {
+    MapBuilder,
+    ShowGeneration,
+    Back,
+}
+
+#[derive(PartialEq)]
+pub enum OptionsResult {
+    NoSelection { selected: OptionsSelection },
+    Change { selected: OptionsSelection },
+    Back,
+}
+
 #[derive(PartialEq)]
 pub enum ItemMenuResult {
     Cancel,
@@ -25,6 +80,16 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     let players = ecs.read_storage::<Player>();
     let log = ecs.fetch::<GameLog>();
     let state = *ecs.fetch::<RunState>();
+    let map = ecs.fetch::<Map>();
+
+    let depth = format!("Depth: {}", map.depth);
+    ctx.print_color(
+        2,
+        43,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &depth,
+    );
 
     for (_player, stats) in (&players, &combat_stats).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
@@ -48,17 +113,62 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
             RGB::named(rltk::BLACK),
         );
 
+        // Newest messages first, wrapping any that are too long for the box
         let mut y = 44;
-        for s in log.entries.iter().rev() {
-            if y < 49 {
-                ctx.print(2, y, s);
+        'entries: for entry in log.entries.iter().rev() {
+            for line in wrap_log_entry(entry, LOG_WIDTH) {
+                if y > 48 {
+                    break 'entries;
+                }
+                draw_log_line(ctx, 2, y, &line);
+                y += 1;
             }
-            y += 1;
         }
 
         if state == RunState::Examining {
             draw_cursor(ecs, ctx);
         }
+        if let RunState::ShowTargeting { range, .. } = state {
+            draw_targeting(ecs, ctx, range);
+        }
+    }
+
+    /// Shades the tiles a ranged item can be aimed at, and shows whether the
+    /// cursor and the tile under the mouse are among them.
+    fn draw_targeting(ecs: &World, ctx: &mut Rltk, range: i32) {
+        let viewport = Viewport::for_player(ecs);
+        let cursor = ecs.fetch::<Cursor>();
+        let tiles = targetable_tiles(ecs, range);
+
+        ctx.print_color(
+            1,
+            0,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            "Select a target: ENTER or click to fire, ESCAPE to cancel",
+        );
+
+        for tile in tiles.iter() {
+            if let Some((x, y)) = viewport.world_to_screen(tile.x, tile.y) {
+                ctx.set_bg(x, y, RGB::named(rltk::BLUE));
+            }
+        }
+
+        let (mouse_x, mouse_y) = ctx.mouse_pos();
+        let highlighted = [
+            viewport.screen_to_world(mouse_x, mouse_y),
+            Some((cursor.x, cursor.y)),
+        ];
+        for (x, y) in highlighted.iter().flatten() {
+            let colour = if tiles.contains(&Point::new(*x, *y)) {
+                RGB::named(rltk::CYAN)
+            } else {
+                RGB::named(rltk::RED)
+            };
+            if let Some((screen_x, screen_y)) = viewport.world_to_screen(*x, *y) {
+                ctx.set_bg(screen_x, screen_y, colour);
+            }
+        }
     }
 
     fn draw_cursor(ecs: &World, ctx: &mut Rltk) {
@@ -66,14 +176,21 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         let names = ecs.read_storage::<Name>();
         let positions = ecs.read_storage::<Position>();
 
+        // The cursor is kept in map coordinates, so find where it is on screen
+        let (cursor_x, cursor_y) =
+            match Viewport::for_player(ecs).world_to_screen(cursor.x, cursor.y) {
+                Some(screen_pos) => screen_pos,
+                None => return,
+            };
+
         let mut tooltip: Vec<String> = Vec::new();
 
         ctx.print_color(
-            cursor.x,
-            cursor.y,
+            cursor_x,
+            cursor_y,
             RGB::named(rltk::WHITE),
             RGB::named(rltk::BLACK),
-            &"X".to_string(),
+            "X",
         );
 
         // Populate tooltip with names of entities under the cursor
@@ -99,7 +216,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
             width_offset += arrow_offset;
 
             // If we're over halfway then flip the toolip
-            if cursor.x > 40 {
+            if cursor_x > VIEW_WIDTH / 2 {
                 arrow = "<-".to_string();
                 width_offset = -(arrow.len() as i32);
                 arrow_offset = 0;
@@ -108,8 +225,8 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
             for s in tooltip.iter() {
                 // Draw tooltip text
                 ctx.print_color(
-                    cursor.x - width_offset,
-                    cursor.y,
+                    cursor_x - width_offset,
+                    cursor_y,
                     RGB::named(rltk::WHITE),
                     RGB::named(rltk::DARK_GRAY),
                     s,
@@ -118,8 +235,8 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
 
             // Draw tooltip arrow
             ctx.print_color(
-                cursor.x - arrow_offset,
-                cursor.y,
+                cursor_x - arrow_offset,
+                cursor_y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::DARK_GRAY),
                 &arrow,
@@ -140,6 +257,7 @@ fn show_item_menu(title: &str, gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
     let entities = gs.ecs.entities();
 
     // Get all of the items in the players backpack
@@ -154,14 +272,14 @@ fn show_item_menu(title: &str, gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult
     let inventory_x = 15;
     let inventory_width = 32;
 
-    let mut y = inventory_y - (count / 2);
+    let y = inventory_y - (count / 2);
 
     // Draw the Inventory box
     ctx.draw_box(
         inventory_x,
         y - 2,
         inventory_width,
-        (count + 3) as i32,
+        count + 3,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
     );
@@ -182,6 +300,8 @@ fn show_item_menu(title: &str, gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult
 
     let mut usable: Vec<Entity> = Vec::new();
     for (i, (entity, _pack, name)) in inventory.iter().enumerate() {
+        let y = y + i as i32;
+
         // Draw the inventory contents
         ctx.set(
             inventory_x + 2,
@@ -206,9 +326,16 @@ fn show_item_menu(title: &str, gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult
         );
 
         ctx.print(inventory_x + 6, y, &name.name.to_string());
+        if equipped.get(*entity).is_some() {
+            ctx.print_color(
+                inventory_x + 7 + name.name.len() as i32,
+                y,
+                RGB::named(rltk::GREY),
+                RGB::named(rltk::BLACK),
+                "(equipped)",
+            );
+        }
         usable.push(*entity);
-
-        y += 1;
     }
 
     match ctx.key {
@@ -225,3 +352,497 @@ fn show_item_menu(title: &str, gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult
         },
     }
 }
+
+/// Splits a log entry into lines no wider than `width`, breaking between
+/// words where possible. Each line keeps the colours of the fragments it
+/// was cut from.
+fn wrap_log_entry(entry: &LogEntry, width: usize) -> Vec<Vec<LogFragment>> {
+    let mut lines: Vec<Vec<LogFragment>> = vec![Vec::new()];
+    let mut line_length = 0;
+
+    for fragment in entry.fragments.iter() {
+        for word in split_words(&fragment.text) {
+            let is_space = word.starts_with(' ');
+            let mut word: Vec<char> = word.chars().collect();
+
+            if is_space && line_length == 0 {
+                continue;
+            }
+            if !is_space && line_length > 0 && line_length + word.len() > width {
+                lines.push(Vec::new());
+                line_length = 0;
+            }
+
+            // Words longer than a whole line are cut wherever they overflow
+            while !word.is_empty() {
+                if line_length == width {
+                    if is_space {
+                        break;
+                    }
+                    lines.push(Vec::new());
+                    line_length = 0;
+                }
+                let take = usize::min(word.len(), width - line_length);
+                let text: String = word.drain(..take).collect();
+                line_length += take;
+
+                let line = lines.last_mut().expect("There is always a line");
+                match line.last_mut() {
+                    Some(last) if last.colour == fragment.colour => last.text.push_str(&text),
+                    _ => line.push(LogFragment {
+                        colour: fragment.colour,
+                        text,
+                    }),
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+/// Splits text into alternating runs of spaces and everything else.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let previous_space = text[start..].starts_with(' ');
+        if i > start && (c == ' ') != previous_space {
+            words.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+fn draw_log_line(ctx: &mut Rltk, x: i32, y: i32, line: &[LogFragment]) {
+    let mut x = x;
+    for fragment in line.iter() {
+        ctx.print_color(
+            x,
+            y,
+            fragment.colour,
+            RGB::named(rltk::BLACK),
+            &fragment.text,
+        );
+        x += fragment.text.chars().count() as i32;
+    }
+}
+
+/// Shows the whole message log, newest at the bottom. `offset` is how many
+/// lines the view has been scrolled back from the newest, counting each line
+/// of a wrapped message separately.
+pub fn show_message_history(ecs: &World, ctx: &mut Rltk) -> MessageHistoryResult {
+    let log = ecs.fetch::<GameLog>();
+    let offset = match *ecs.fetch::<RunState>() {
+        RunState::MessageHistory { offset } => offset,
+        _ => 0,
+    };
+
+    let lines: Vec<Vec<LogFragment>> = log
+        .entries
+        .iter()
+        .flat_map(|entry| wrap_log_entry(entry, LOG_WIDTH))
+        .collect();
+
+    let page_height: usize = 45;
+    let max_offset = lines.len().saturating_sub(page_height);
+    let offset = offset.min(max_offset);
+
+    ctx.draw_box(
+        0,
+        0,
+        79,
+        49,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        3,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Message History",
+    );
+    ctx.print_color(
+        3,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "UP/DOWN to scroll, PAGE UP/PAGE DOWN to page, ESCAPE to close",
+    );
+
+    // Show the page of lines ending `offset` lines back from the newest,
+    // keeping the newest line at the bottom when the page isn't full
+    let end = lines.len() - offset;
+    let start = end.saturating_sub(page_height);
+    let top = 2 + (page_height - (end - start)) as i32;
+    for (y, line) in (top..).zip(lines[start..end].iter()) {
+        draw_log_line(ctx, 2, y, line);
+    }
+
+    if offset < max_offset {
+        ctx.print_color(
+            76,
+            1,
+            RGB::named(rltk::GRAY),
+            RGB::named(rltk::BLACK),
+            "...",
+        );
+    }
+    if offset > 0 {
+        ctx.print_color(
+            76,
+            47,
+            RGB::named(rltk::GRAY),
+            RGB::named(rltk::BLACK),
+            "...",
+        );
+    }
+
+    let offset = match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::M) => {
+            return MessageHistoryResult::Close
+        }
+        Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::Numpad8) | Some(VirtualKeyCode::K) => {
+            offset + 1
+        }
+        Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::Numpad2) | Some(VirtualKeyCode::J) => {
+            offset.saturating_sub(1)
+        }
+        Some(VirtualKeyCode::PageUp) => offset + page_height,
+        Some(VirtualKeyCode::PageDown) => offset.saturating_sub(page_height),
+        Some(VirtualKeyCode::Home) => max_offset,
+        Some(VirtualKeyCode::End) => 0,
+        _ => offset,
+    };
+
+    MessageHistoryResult::NoResponse {
+        offset: offset.min(max_offset),
+    }
+}
+
+/// Shows how the run ended, and asks whether to play again.
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let game_stats = ecs.fetch::<GameStats>();
+    let map = ecs.fetch::<Map>();
+
+    let cause = match &game_stats.last_hit_by {
+        Some(name) => format!("You were slain by the {} on depth {}.", name, map.depth),
+        None => format!("You died on depth {}.", map.depth),
+    };
+    let turns = format!(
+        "You survived for {} turns and slew {} monsters.",
+        game_stats.turns,
+        game_stats.kills.len()
+    );
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::RED),
+        RGB::named(rltk::BLACK),
+        "Your journey has ended!",
+    );
+    ctx.print_color_centered(18, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &cause);
+    ctx.print_color_centered(19, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &turns);
+    ctx.print_color_centered(
+        22,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Press ENTER to start a new game, or ESCAPE for the main menu.",
+    );
+    draw_highscores(ecs, ctx, 25, 5);
+
+    match ctx.key {
+        Some(VirtualKeyCode::Return) => GameOverResult::NewGame,
+        Some(VirtualKeyCode::Escape) => GameOverResult::MainMenu,
+        _ => GameOverResult::NoSelection,
+    }
+}
+
+/// Shows the title screen. Continue is only offered when there is a saved
+/// game to continue from.
+pub fn main_menu(ecs: &World, ctx: &mut Rltk) -> MainMenuResult {
+    let mut entries = vec![MainMenuSelection::NewGame];
+    if saveload_system::does_save_exist() {
+        entries.push(MainMenuSelection::Continue);
+    }
+    entries.push(MainMenuSelection::HighScores);
+    entries.push(MainMenuSelection::Options);
+    entries.push(MainMenuSelection::Quit);
+
+    let selected = match *ecs.fetch::<RunState>() {
+        RunState::MainMenu { menu_selection } if entries.contains(&menu_selection) => {
+            menu_selection
+        }
+        _ => entries[0],
+    };
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Rustlike",
+    );
+
+    let labels: Vec<String> = entries
+        .iter()
+        .map(|entry| match entry {
+            MainMenuSelection::NewGame => "Begin New Game",
+            MainMenuSelection::Continue => "Continue Saved Game",
+            MainMenuSelection::HighScores => "High Scores",
+            MainMenuSelection::Options => "Options",
+            MainMenuSelection::Quit => "Quit",
+        })
+        .map(str::to_string)
+        .collect();
+    let position = entries
+        .iter()
+        .position(|entry| *entry == selected)
+        .unwrap_or(0);
+    draw_menu_entries(ctx, 24, &labels, position);
+
+    match menu_key(ctx, position, entries.len()) {
+        MenuKey::Moved(position) => MainMenuResult::NoSelection {
+            selected: entries[position],
+        },
+        MenuKey::Chosen => MainMenuResult::Selected { selected },
+        _ => MainMenuResult::NoSelection { selected },
+    }
+}
+
+/// Shows the full high score table until ESCAPE or ENTER is pressed.
+pub fn show_highscores(ecs: &World, ctx: &mut Rltk) -> HighScoresResult {
+    draw_highscores(ecs, ctx, 12, usize::MAX);
+    ctx.print_color_centered(
+        40,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "Press ESCAPE to return to the main menu.",
+    );
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::Return) => HighScoresResult::Back,
+        _ => HighScoresResult::NoResponse,
+    }
+}
+
+/// Draws up to `count` of the best runs from `y` downwards, highlighting the
+/// run that has just ended if it made the table.
+fn draw_highscores(ecs: &World, ctx: &mut Rltk, y: i32, count: usize) {
+    let table = ecs.fetch::<HighScoreTable>();
+
+    ctx.print_color_centered(
+        y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "High Scores",
+    );
+    if table.entries.is_empty() {
+        ctx.print_color_centered(
+            y + 2,
+            RGB::named(rltk::GRAY),
+            RGB::named(rltk::BLACK),
+            "No runs have been recorded yet.",
+        );
+        return;
+    }
+
+    for (i, entry) in table.entries.iter().take(count).enumerate() {
+        let fg = if table.latest == Some(i) {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        let line = format!(
+            "{:>2}. {:>6}  depth {:>2}  {:>3} kills  {:>5} turns",
+            i + 1,
+            entry.score,
+            entry.depth,
+            entry.kills,
+            entry.turns
+        );
+        let row = y + 2 + i as i32 * 2;
+        ctx.print_color_centered(row, fg, RGB::named(rltk::BLACK), &line);
+        ctx.print_color_centered(
+            row + 1,
+            RGB::named(rltk::GRAY),
+            RGB::named(rltk::BLACK),
+            &entry.replay_args(),
+        );
+    }
+}
+
+/// Shows the settings used for the next level generated. Left, right or
+/// ENTER changes the highlighted setting.
+pub fn options_menu(ecs: &World, ctx: &mut Rltk) -> OptionsResult {
+    let entries = [
+        OptionsSelection::MapBuilder,
+        OptionsSelection::ShowGeneration,
+        OptionsSelection::Back,
+    ];
+    let selected = match *ecs.fetch::<RunState>() {
+        RunState::Options { selected } => selected,
+        _ => entries[0],
+    };
+    let config = ecs.fetch::<MapGenConfig>();
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Options",
+    );
+
+    let labels: Vec<String> = entries
+        .iter()
+        .map(|entry| match entry {
+            OptionsSelection::MapBuilder => format!(
+                "Level generator: {}",
+                config.builder.as_deref().unwrap_or("random")
+            ),
+            OptionsSelection::ShowGeneration => format!(
+                "Watch levels being generated: {}",
+                if config.show_generation { "on" } else { "off" }
+            ),
+            OptionsSelection::Back => "Back".to_string(),
+        })
+        .collect();
+    let position = entries
+        .iter()
+        .position(|entry| *entry == selected)
+        .unwrap_or(0);
+    draw_menu_entries(ctx, 24, &labels, position);
+
+    match menu_key(ctx, position, entries.len()) {
+        MenuKey::Moved(position) => OptionsResult::NoSelection {
+            selected: entries[position],
+        },
+        MenuKey::Chosen if selected == OptionsSelection::Back => OptionsResult::Back,
+        MenuKey::Chosen | MenuKey::Changed if selected != OptionsSelection::Back => {
+            OptionsResult::Change { selected }
+        }
+        MenuKey::Cancelled => OptionsResult::Back,
+        _ => OptionsResult::NoSelection { selected },
+    }
+}
+
+/// What a key press did to a vertical menu.
+enum MenuKey {
+    Nothing,
+    Moved(usize),
+    Chosen,
+    Changed,
+    Cancelled,
+}
+
+fn menu_key(ctx: &Rltk, position: usize, count: usize) -> MenuKey {
+    match ctx.key {
+        Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::Numpad8) | Some(VirtualKeyCode::K) => {
+            MenuKey::Moved((position + count - 1) % count)
+        }
+        Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::Numpad2) | Some(VirtualKeyCode::J) => {
+            MenuKey::Moved((position + 1) % count)
+        }
+        Some(VirtualKeyCode::Left)
+        | Some(VirtualKeyCode::Right)
+        | Some(VirtualKeyCode::Numpad4)
+        | Some(VirtualKeyCode::Numpad6) => MenuKey::Changed,
+        Some(VirtualKeyCode::Return) => MenuKey::Chosen,
+        Some(VirtualKeyCode::Escape) => MenuKey::Cancelled,
+        _ => MenuKey::Nothing,
+    }
+}
+
+/// Draws a centred list of menu entries from `y` downwards, highlighting the
+/// selected one.
+fn draw_menu_entries(ctx: &mut Rltk, y: i32, labels: &[String], selected: usize) {
+    for (i, label) in labels.iter().enumerate() {
+        let fg = if i == selected {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(y + i as i32, fg, RGB::named(rltk::BLACK), label);
+    }
+    ctx.print_color_centered(
+        y + labels.len() as i32 + 2,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "Use the arrow keys to choose, and ENTER to select.",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(fragments: &[(&str, RGB)]) -> LogEntry {
+        LogEntry {
+            fragments: fragments
+                .iter()
+                .map(|(text, colour)| LogFragment {
+                    colour: *colour,
+                    text: text.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Each wrapped line as its coloured runs of text.
+    fn runs(lines: &[Vec<LogFragment>]) -> Vec<Vec<(String, RGB)>> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|f| (f.text.clone(), f.colour)).collect())
+            .collect()
+    }
+
+    fn texts(lines: &[Vec<LogFragment>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|f| f.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn words_longer_than_a_line_are_cut() {
+        let white = RGB::named(rltk::WHITE);
+        let lines = wrap_log_entry(&entry(&[("a abcdefghij", white)]), 4);
+
+        assert_eq!(texts(&lines), vec!["a ", "abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn text_exactly_at_the_width_fits_on_one_line() {
+        let white = RGB::named(rltk::WHITE);
+
+        let lines = wrap_log_entry(&entry(&[("abcd", white)]), 4);
+        assert_eq!(texts(&lines), vec!["abcd"]);
+
+        // Spaces at the break are dropped rather than starting the next line
+        let lines = wrap_log_entry(&entry(&[("abcd  efgh", white)]), 4);
+        assert_eq!(texts(&lines), vec!["abcd", "efgh"]);
+    }
+
+    #[test]
+    fn colours_are_kept_across_line_breaks() {
+        let white = RGB::named(rltk::WHITE);
+        let red = RGB::named(rltk::RED);
+        let lines = wrap_log_entry(
+            &entry(&[("the ", white), ("orc warrior", red), (" hits", white)]),
+            10,
+        );
+
+        assert_eq!(
+            runs(&lines),
+            vec![
+                vec![("the ".to_string(), white), ("orc ".to_string(), red)],
+                vec![("warrior".to_string(), red), (" ".to_string(), white)],
+                vec![("hits".to_string(), white)],
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+extern crate specs;
+use super::{GameStats, Map, MapGenConfig, RunSeed, MAPHEIGHT, MAPWIDTH};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const HIGHSCORE_FILE: &str = "./highscores.json";
+
+/// Where an unreadable high score file is moved to, so that it isn't lost
+/// when the next score is written.
+const CORRUPT_HIGHSCORE_FILE: &str = "./highscores.json.corrupt";
+
+/// How many runs are kept on the table.
+const MAX_HIGHSCORES: usize = 10;
+
+const POINTS_PER_DEPTH: i32 = 100;
+const POINTS_PER_KILL: i32 = 10;
+const TURNS_PER_POINT: i32 = 10;
+
+/// A finished run, with the seed and level settings it was played on so that
+/// it can be replayed.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScore {
+    pub score: i32,
+    pub depth: i32,
+    pub kills: i32,
+    pub turns: i32,
+    pub seed: u64,
+    pub slain_by: Option<String>,
+    /// The level generator used, or `None` if one was picked at random.
+    pub builder: Option<String>,
+    /// Runs recorded before the map size was kept were played at the default.
+    #[serde(default = "default_map_width")]
+    pub map_width: i32,
+    #[serde(default = "default_map_height")]
+    pub map_height: i32,
+}
+
+fn default_map_width() -> i32 {
+    MAPWIDTH
+}
+
+fn default_map_height() -> i32 {
+    MAPHEIGHT
+}
+
+impl HighScore {
+    /// The command line flags that play this run's dungeon again.
+    pub fn replay_args(&self) -> String {
+        let mut args = format!("--seed {}", self.seed);
+        if let Some(builder) = &self.builder {
+            args.push_str(&format!(" --map-builder {}", builder));
+        }
+        args.push_str(&format!(
+            " --map-width {} --map-height {}",
+            self.map_width, self.map_height
+        ));
+        args
+    }
+}
+
+/// The best runs so far, highest score first, along with where the most
+/// recent run placed if it made the table.
+#[derive(Default)]
+pub struct HighScoreTable {
+    pub entries: Vec<HighScore>,
+    pub latest: Option<usize>,
+}
+
+/// Deeper runs always beat shallower ones with the same kills, with turns
+/// survived counting for the least.
+pub fn score(depth: i32, kills: i32, turns: i32) -> i32 {
+    depth * POINTS_PER_DEPTH + kills * POINTS_PER_KILL + turns / TURNS_PER_POINT
+}
+
+impl HighScoreTable {
+    /// Reads the high score file. A missing file is an empty table, and an
+    /// unreadable one is set aside and replaced with an empty table.
+    pub fn load() -> HighScoreTable {
+        if !Path::new(HIGHSCORE_FILE).exists() {
+            return HighScoreTable::default();
+        }
+
+        let parsed = fs::read_to_string(HIGHSCORE_FILE)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                serde_json::from_str::<Vec<HighScore>>(&data).map_err(|e| e.to_string())
+            });
+
+        match parsed {
+            Ok(mut entries) => {
+                entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+                entries.truncate(MAX_HIGHSCORES);
+                HighScoreTable {
+                    entries,
+                    latest: None,
+                }
+            }
+            Err(e) => {
+                rltk::console::log(format!("Unable to read high scores: {}", e));
+                if let Err(e) = fs::rename(HIGHSCORE_FILE, CORRUPT_HIGHSCORE_FILE) {
+                    rltk::console::log(format!("Unable to move high scores aside: {}", e));
+                }
+                HighScoreTable::default()
+            }
+        }
+    }
+
+    /// Adds a run to the table, keeping it in order and trimmed to size, and
+    /// returns the place it earned, if any.
+    pub fn add(&mut self, entry: HighScore) -> Option<usize> {
+        let place = self
+            .entries
+            .iter()
+            .position(|existing| entry.score > existing.score)
+            .unwrap_or(self.entries.len());
+
+        self.latest = if place < MAX_HIGHSCORES {
+            self.entries.insert(place, entry);
+            self.entries.truncate(MAX_HIGHSCORES);
+            Some(place)
+        } else {
+            None
+        };
+
+        self.latest
+    }
+
+    /// Writes the table out to the high score file.
+    pub fn save(&self) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(&self.entries)?;
+        fs::write(HIGHSCORE_FILE, data)
+    }
+}
+
+/// Scores the run that has just ended and adds it to the high score table,
+/// writing the table back out if the run made it on. Returns the score and
+/// the place earned.
+pub fn record_highscore(ecs: &World) -> io::Result<(i32, Option<usize>)> {
+    let game_stats = ecs.fetch::<GameStats>();
+    let depth = ecs.fetch::<Map>().depth;
+    let kills = game_stats.kills.len() as i32;
+    let map_config = ecs.fetch::<MapGenConfig>();
+
+    let entry = HighScore {
+        score: score(depth, kills, game_stats.turns),
+        depth,
+        kills,
+        turns: game_stats.turns,
+        seed: ecs.fetch::<RunSeed>().seed,
+        slain_by: game_stats.last_hit_by.clone(),
+        builder: map_config.builder.clone(),
+        map_width: map_config.width,
+        map_height: map_config.height,
+    };
+    let points = entry.score;
+
+    let mut table = ecs.fetch_mut::<HighScoreTable>();
+    let place = table.add(entry);
+    if place.is_some() {
+        table.save()?;
+    }
+
+    Ok((points, place))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_without_level_settings_replay_at_the_default_size() {
+        let data = r#"{"score":120,"depth":1,"kills":2,"turns":10,"seed":7,"slain_by":null}"#;
+        let entry: HighScore = serde_json::from_str(data).expect("Unable to read entry");
+
+        assert!(entry.builder.is_none());
+        assert_eq!(
+            entry.replay_args(),
+            format!(
+                "--seed 7 --map-width {} --map-height {}",
+                MAPWIDTH, MAPHEIGHT
+            )
+        );
+    }
+
+    #[test]
+    fn replay_args_name_the_builder() {
+        let entry = HighScore {
+            score: 0,
+            depth: 1,
+            kills: 0,
+            turns: 0,
+            seed: 42,
+            slain_by: None,
+            builder: Some("bsp".to_string()),
+            map_width: 100,
+            map_height: 60,
+        };
+
+        assert_eq!(
+            entry.replay_args(),
+            "--seed 42 --map-builder bsp --map-width 100 --map-height 60"
+        );
+    }
+}
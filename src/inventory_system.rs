@@ -1,33 +1,37 @@
 extern crate specs;
 use super::{
-    CombatStats, GameLog, InBackpack, Name, Position, Potion, WantsToDrinkPotion, WantsToDropItem,
+    scheduler, AreaOfEffect, CombatStats, Confused, Confusion, Consumable, Energy, Equippable,
+    Equipped, EventQueue, GameEvent, InBackpack, InflictsDamage, Map, Monster, Point, Position,
+    ProvidesHealing, SufferDamage, WantsToDropItem, WantsToEquip, WantsToUnequip, WantsToUseItem,
 };
 use specs::prelude::*;
 
-pub struct PotionUseSystem {}
+pub struct ItemUseSystem {}
 pub struct ItemDropSystem {}
+pub struct ItemEquipSystem {}
+pub struct ItemUnequipSystem {}
 
 impl<'a> System<'a> for ItemDropSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        ReadExpect<'a, Entity>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EventQueue>,
         Entities<'a>,
         WriteStorage<'a, WantsToDropItem>,
-        ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Energy>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            player_entity,
-            mut gamelog,
+            mut events,
             entities,
             mut wants_drop,
-            names,
             mut positions,
             mut backpack,
+            mut equipped,
+            mut energies,
         ) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
@@ -47,58 +51,238 @@ impl<'a> System<'a> for ItemDropSystem {
                 )
                 .expect("Unable to insert position");
             backpack.remove(to_drop.item);
+            equipped.remove(to_drop.item);
 
-            if entity == *player_entity {
-                gamelog.entries.push(format!(
-                    "You drop the {}.",
-                    names.get(to_drop.item).unwrap().name
-                ))
-            }
+            scheduler::spend_energy(&mut energies, entity, scheduler::DROP_COST);
+            events.emit(GameEvent::ItemDropped {
+                dropper: entity,
+                item: to_drop.item,
+            });
         }
 
         wants_drop.clear();
     }
 }
 
-impl<'a> System<'a> for PotionUseSystem {
+impl<'a> System<'a> for ItemUseSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        ReadExpect<'a, Entity>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EventQueue>,
+        ReadExpect<'a, Map>,
         Entities<'a>,
-        WriteStorage<'a, WantsToDrinkPotion>,
-        ReadStorage<'a, Name>,
-        ReadStorage<'a, Potion>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Consumable>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, Confusion>,
+        WriteStorage<'a, Confused>,
+        ReadStorage<'a, Monster>,
         WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Energy>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            player_entity,
-            mut gamelog,
+            mut events,
+            map,
             entities,
-            mut wants_drink,
-            names,
-            potions,
+            mut wants_use,
+            consumables,
+            healing,
+            inflicts_damage,
+            area_of_effect,
+            confusion,
+            mut confused,
+            monsters,
             mut combat_stats,
+            mut suffer_damage,
+            mut energies,
         ) = data;
 
-        for (entity, drink, stats) in (&entities, &wants_drink, &mut combat_stats).join() {
-            let potion = potions.get(drink.potion);
-            if let Some(potion) = potion {
-                // Don't heal over max health
-                stats.hp = i32::min(stats.max_hp, stats.hp + potion.heal_amount);
-                if entity == *player_entity {
-                    gamelog.entries.push(format!(
-                        "You drink the {}, healing {} hp.",
-                        names.get(drink.potion).unwrap().name,
-                        potion.heal_amount
-                    ));
+        for (entity, use_item) in (&entities, &wants_use).join() {
+            let item = use_item.item;
+            let targets = targets(&map, use_item.target, area_of_effect.get(item), entity);
+
+            if let Some(healing) = healing.get(item) {
+                for target in targets.iter() {
+                    if let Some(stats) = combat_stats.get_mut(*target) {
+                        // Don't heal over max health
+                        stats.hp = i32::min(stats.max_hp, stats.hp + healing.heal_amount);
+                        events.emit(GameEvent::Healed {
+                            entity: *target,
+                            item,
+                            amount: healing.heal_amount,
+                        });
+                    }
+                }
+            }
+
+            if let Some(damage) = inflicts_damage.get(item) {
+                for target in targets.iter() {
+                    if combat_stats.get(*target).is_none() {
+                        continue;
+                    }
+
+                    // Add to any damage the target is already due to take
+                    match suffer_damage.get_mut(*target) {
+                        Some(suffering) => suffering.amount += damage.damage,
+                        None => {
+                            suffer_damage
+                                .insert(
+                                    *target,
+                                    SufferDamage {
+                                        amount: damage.damage,
+                                    },
+                                )
+                                .expect("Could not insert damage.");
+                        }
+                    }
+                    events.emit(GameEvent::ItemHit {
+                        user: entity,
+                        item,
+                        target: *target,
+                        damage: damage.damage,
+                    });
                 }
-                entities.delete(drink.potion).expect("Delete failed");
+            }
+
+            if let Some(confusion) = confusion.get(item) {
+                for target in targets.iter() {
+                    // Only monsters' minds can be muddled
+                    if monsters.get(*target).is_none() {
+                        continue;
+                    }
+
+                    confused
+                        .insert(
+                            *target,
+                            Confused {
+                                turns: confusion.turns,
+                            },
+                        )
+                        .expect("Unable to confuse target.");
+                    events.emit(GameEvent::Confused {
+                        item,
+                        target: *target,
+                    });
+                }
+            }
+
+            scheduler::spend_energy(&mut energies, entity, scheduler::USE_ITEM_COST);
+            if consumables.get(item).is_some() {
+                entities.delete(item).expect("Delete failed");
+            }
+        }
+
+        wants_use.clear();
+    }
+}
+
+/// Everything an item used on a point affects: whatever is on that tile, or
+/// on any tile in sight of it if the item has an area of effect. Items used
+/// without a target affect their user.
+fn targets(
+    map: &Map,
+    target: Option<Point>,
+    area: Option<&AreaOfEffect>,
+    user: Entity,
+) -> Vec<Entity> {
+    let target = match target {
+        Some(target) => target,
+        None => return vec![user],
+    };
+
+    let tiles = match area {
+        Some(area) => rltk::field_of_view(target, area.radius, map),
+        None => vec![target],
+    };
+
+    tiles
+        .iter()
+        .filter(|tile| map.is_in_bounds(tile.x, tile.y))
+        .flat_map(|tile| map.tile_content[map.xy_idx(tile.x, tile.y)].iter().copied())
+        .collect()
+}
+
+impl<'a> System<'a> for ItemEquipSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, EventQueue>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToEquip>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Energy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut events, entities, mut wants_equip, equippable, mut equipped, mut energies) = data;
+
+        for (entity, to_equip) in (&entities, &wants_equip).join() {
+            let slot = match equippable.get(to_equip.item) {
+                Some(equippable) => equippable.slot,
+                None => continue,
+            };
+
+            // Take off whatever is already in the slot
+            let in_slot: Vec<Entity> = (&entities, &equipped)
+                .join()
+                .filter(|(_, equipped)| equipped.owner == entity && equipped.slot == slot)
+                .map(|(item, _)| item)
+                .collect();
+            for item in in_slot {
+                equipped.remove(item);
+                events.emit(GameEvent::ItemUnequipped {
+                    owner: entity,
+                    item,
+                });
+            }
+
+            equipped
+                .insert(
+                    to_equip.item,
+                    Equipped {
+                        owner: entity,
+                        slot,
+                    },
+                )
+                .expect("Unable to equip item");
+
+            scheduler::spend_energy(&mut energies, entity, scheduler::EQUIP_COST);
+            events.emit(GameEvent::ItemEquipped {
+                owner: entity,
+                item: to_equip.item,
+            });
+        }
+
+        wants_equip.clear();
+    }
+}
+
+impl<'a> System<'a> for ItemUnequipSystem {
+    type SystemData = (
+        WriteExpect<'a, EventQueue>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUnequip>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Energy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut events, entities, mut wants_unequip, mut equipped, mut energies) = data;
+
+        for (entity, to_unequip) in (&entities, &wants_unequip).join() {
+            if equipped.remove(to_unequip.item).is_some() {
+                scheduler::spend_energy(&mut energies, entity, scheduler::EQUIP_COST);
+                events.emit(GameEvent::ItemUnequipped {
+                    owner: entity,
+                    item: to_unequip.item,
+                });
             }
         }
 
-        wants_drink.clear();
+        wants_unequip.clear();
     }
 }
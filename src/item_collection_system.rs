@@ -1,23 +1,20 @@
 extern crate specs;
-use super::{GameLog, InBackpack, Name, Position, WantsToPickupItem};
+use super::{scheduler, Energy, EventQueue, GameEvent, InBackpack, Position, WantsToPickupItem};
 use specs::prelude::*;
 
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
-    #[allow(clippy::type_complexity)]
     type SystemData = (
-        ReadExpect<'a, Entity>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EventQueue>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
-        WriteStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Energy>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (mut events, mut wants_pickup, mut positions, mut backpack, mut energies) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
@@ -29,12 +26,11 @@ impl<'a> System<'a> for ItemCollectionSystem {
                     },
                 )
                 .expect("Unable to insert backpack entry");
-            if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!(
-                    "You pick up the {}.",
-                    names.get(pickup.item).unwrap().name
-                ));
-            }
+            scheduler::spend_energy(&mut energies, pickup.collected_by, scheduler::PICKUP_COST);
+            events.emit(GameEvent::ItemPickedUp {
+                collector: pickup.collected_by,
+                item: pickup.item,
+            });
         }
 
         wants_pickup.clear();
@@ -4,33 +4,61 @@ use specs::prelude::*;
 #[macro_use]
 extern crate specs_derive;
 
+mod achievements;
+mod camera;
 mod components;
 mod damage_system;
+mod events;
+mod flow_field_system;
 mod gamelog;
+mod gamestats;
 mod gui;
+mod highscores;
 mod inventory_system;
 mod item_collection_system;
 mod map;
+mod map_builders;
 mod map_indexing_system;
 mod melee_combat_system;
 mod monster_ai_system;
+mod morgue;
 mod player;
+mod random_table;
+mod raws;
 mod rect;
+mod rng;
+mod saveload_system;
+mod scheduler;
+mod simulation;
+mod sound;
 mod spawner;
 mod visibility_system;
 
+use achievements::*;
+use camera::*;
 pub use components::*;
 use damage_system::*;
+use events::*;
+use flow_field_system::*;
 use gamelog::*;
+use gamestats::*;
+use highscores::HighScoreTable;
 use inventory_system::*;
 use item_collection_system::*;
 pub use map::*;
+use map_builders::{MapBuilder, MapGenConfig, MapGenHistory};
 use map_indexing_system::*;
 use melee_combat_system::*;
 use monster_ai_system::*;
 use player::*;
+use random_table::*;
+use raws::*;
 use rect::*;
 use rltk::RltkBuilder;
+use rng::RandomNumberGenerator;
+use sound::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+use std::str::FromStr;
 use visibility_system::*;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
@@ -42,16 +70,46 @@ pub enum RunState {
     Examining,
     ShowInventory,
     ShowDropItem,
+    /// Choosing where to aim a ranged item from the inventory.
+    ShowTargeting {
+        range: i32,
+        item: Entity,
+    },
+    NextLevel,
+    SaveGame,
+    MapGeneration,
+    GameOver,
+    MainMenu {
+        menu_selection: gui::MainMenuSelection,
+    },
+    Options {
+        selected: gui::OptionsSelection,
+    },
+    HighScores,
+    MessageHistory {
+        offset: usize,
+    },
 }
 
+/// How long each snapshot of a level's generation is shown for.
+const MAPGEN_FRAME_MS: f32 = 100.0;
+
 pub struct State {
     pub ecs: World,
+    /// The seed asked for on the command line, used by the first new game.
+    pub next_seed: Option<u64>,
+    /// Whether the current run has ended and been recorded.
+    pub run_ended: bool,
 }
 
 impl State {
     fn run_systems(&mut self) {
+        self.ecs.write_resource::<EventQueue>().clear();
+
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut flow_fields = FlowFieldSystem {};
+        flow_fields.run_now(&self.ecs);
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
         let mut map_idx = MapIndexingSystem {};
@@ -62,58 +120,253 @@ impl State {
         melee.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem {};
         pickup.run_now(&self.ecs);
-        let mut potions = PotionUseSystem {};
-        potions.run_now(&self.ecs);
+        let mut use_items = ItemUseSystem {};
+        use_items.run_now(&self.ecs);
         let mut drop_items = ItemDropSystem {};
         drop_items.run_now(&self.ecs);
+        let mut equip = ItemEquipSystem {};
+        equip.run_now(&self.ecs);
+        let mut unequip = ItemUnequipSystem {};
+        unequip.run_now(&self.ecs);
+
+        // Everything that reacts to this turn's events
+        let mut event_log = EventLogSystem {};
+        event_log.run_now(&self.ecs);
+        let mut statistics = StatisticsSystem {};
+        statistics.run_now(&self.ecs);
+        let mut achievements = AchievementSystem {};
+        achievements.run_now(&self.ecs);
+        let mut sounds = SoundSystem {};
+        sounds.run_now(&self.ecs);
 
         self.ecs.maintain();
     }
-}
-
-impl GameState for State {
-    fn tick(&mut self, ctx: &mut Rltk) {
-        ctx.cls();
 
-        {
-            draw_map(&self.ecs, ctx);
+    /// Collects every entity that should not survive a level change, that is
+    /// everything except the player and the items they are carrying.
+    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player = self.ecs.read_storage::<Player>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+        let player_entity = self.ecs.fetch::<Entity>();
 
-            let positions = self.ecs.read_storage::<Position>();
-            let renderables = self.ecs.read_storage::<Renderable>();
-            let map = self.ecs.fetch::<Map>();
+        let mut to_delete: Vec<Entity> = Vec::new();
+        for entity in entities.join() {
+            if player.get(entity).is_some() {
+                continue;
+            }
 
-            for (pos, render) in (&positions, &renderables).join() {
-                let idx = map.xy_idx(pos.x, pos.y);
-                if map.visible_tiles[idx] {
-                    ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
+            if let Some(pack) = backpack.get(entity) {
+                if pack.owner == *player_entity {
+                    continue;
                 }
             }
 
-            gui::draw_ui(&self.ecs, ctx);
+            to_delete.push(entity);
         }
 
-        let mut current_runstate: RunState;
-        {
-            let runstate = self.ecs.fetch::<RunState>();
-            current_runstate = *runstate;
+        to_delete
+    }
+
+    /// Throws away the current run and starts a new one with a fresh seed,
+    /// keeping the raws and map settings the game was started with.
+    fn start_new_game(&mut self) {
+        let raws = (*self.ecs.fetch::<RawMaster>()).clone();
+        let map_config = (*self.ecs.fetch::<MapGenConfig>()).clone();
+        let highscores = self
+            .ecs
+            .remove::<HighScoreTable>()
+            .expect("No high scores to carry over");
+        let seed = self
+            .next_seed
+            .take()
+            .unwrap_or_else(|| rltk::RandomNumberGenerator::new().next_u64());
+        self.ecs = new_game(seed, &raws, &map_config);
+        self.ecs.insert(highscores);
+        self.run_ended = false;
+    }
+
+    /// Records a run that has ended in the player's death, once only.
+    fn end_run(&mut self) {
+        if self.run_ended {
+            return;
         }
+        self.run_ended = true;
 
-        match current_runstate {
+        let score_message = match highscores::record_highscore(&self.ecs) {
+            Ok((score, Some(place))) => format!(
+                "You scored {} points, placing #{} on the high score table.",
+                score,
+                place + 1
+            ),
+            Ok((score, None)) => format!("You scored {} points.", score),
+            Err(e) => format!("Unable to save high scores: {}", e),
+        };
+        let morgue_message = match morgue::write_morgue(&self.ecs) {
+            Ok(path) => format!("A record of your run was written to {}.", path.display()),
+            Err(e) => format!("Unable to write morgue file: {}", e),
+        };
+
+        let mut log = self.ecs.fetch_mut::<GameLog>();
+        log.push(score_message);
+        log.push(morgue_message);
+    }
+
+    /// Plays out the states that make up a turn, for both the interactive
+    /// game and headless runs. `player_input` decides what the player does
+    /// once they are able to act. Any other state is returned unchanged.
+    fn play_turn<F>(&mut self, runstate: RunState, player_input: F) -> RunState
+    where
+        F: FnOnce(&mut State) -> RunState,
+    {
+        match runstate {
             RunState::PreRun => {
                 self.run_systems();
-                current_runstate = RunState::AwaitingInput;
+                RunState::AwaitingInput
             }
             RunState::AwaitingInput | RunState::Examining => {
                 self.run_systems();
-                current_runstate = player_input(self, ctx);
+                // Systems may have just killed the player, who must not act
+                if is_player_dead(&self.ecs) {
+                    RunState::GameOver
+                } else if scheduler::player_can_act(&self.ecs) {
+                    player_input(self)
+                } else {
+                    RunState::MonsterTurn
+                }
             }
             RunState::PlayerTurn => {
                 self.run_systems();
-                current_runstate = RunState::MonsterTurn;
+                RunState::MonsterTurn
             }
             RunState::MonsterTurn => {
                 self.run_systems();
-                current_runstate = RunState::AwaitingInput;
+                scheduler::next_turn(&mut self.ecs)
+            }
+            RunState::NextLevel => {
+                self.goto_next_level();
+                state_after_generation(&self.ecs)
+            }
+            _ => runstate,
+        }
+    }
+
+    /// Acts on the choice made on the game over screen. The dead player's
+    /// run is thrown away on leaving it, so the main menu gets a fresh world.
+    fn game_over_choice(&mut self, result: gui::GameOverResult) -> RunState {
+        match result {
+            gui::GameOverResult::NoSelection => RunState::GameOver,
+            gui::GameOverResult::NewGame => {
+                self.start_new_game();
+                *self.ecs.fetch::<RunState>()
+            }
+            gui::GameOverResult::MainMenu => {
+                self.start_new_game();
+                RunState::MainMenu {
+                    menu_selection: gui::MainMenuSelection::NewGame,
+                }
+            }
+        }
+    }
+
+    /// Stores the state for the next tick, then clears away the dead and
+    /// ends the run if the player was among them.
+    fn finish_tick(&mut self, runstate: RunState) {
+        *self.ecs.write_resource::<RunState>() = runstate;
+
+        DamageSystem::delete_the_dead(&mut self.ecs);
+
+        if *self.ecs.fetch::<RunState>() == RunState::GameOver {
+            self.end_run();
+        }
+    }
+
+    /// Tears down the current level and builds the next one down.
+    fn goto_next_level(&mut self) {
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        let config = (*self.ecs.fetch::<MapGenConfig>()).clone();
+        let mut builder = {
+            let raws = self.ecs.fetch::<RawMaster>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+            let mut builder =
+                map_builders::create_builder(&config, &raws, current_depth + 1, &mut rng);
+            builder.build_map(&mut rng);
+            builder
+        };
+        builder.spawn_entities(&mut self.ecs);
+        record_generation(&mut self.ecs, &config, builder.as_ref());
+        let map = builder.get_map();
+        let start = builder.get_starting_position();
+        let (player_x, player_y) = (start.x, start.y);
+
+        {
+            let mut worldmap = self.ecs.write_resource::<Map>();
+            *worldmap = map;
+        }
+
+        let player_entity = *self.ecs.fetch::<Entity>();
+        {
+            let mut player_position = self.ecs.write_resource::<Point>();
+            *player_position = Point::new(player_x, player_y);
+
+            let mut positions = self.ecs.write_storage::<Position>();
+            if let Some(player_pos) = positions.get_mut(player_entity) {
+                player_pos.x = player_x;
+                player_pos.y = player_y;
+            }
+
+            let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+            if let Some(viewshed) = viewsheds.get_mut(player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+
+        let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+        gamelog.push(format!(
+            "You descend the stairs to depth {}.",
+            current_depth + 1
+        ));
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        ctx.cls();
+
+        let mut current_runstate: RunState;
+        {
+            let runstate = self.ecs.fetch::<RunState>();
+            current_runstate = *runstate;
+        }
+
+        match current_runstate {
+            RunState::MapGeneration
+            | RunState::GameOver
+            | RunState::MainMenu { .. }
+            | RunState::Options { .. }
+            | RunState::HighScores
+            | RunState::MessageHistory { .. } => {}
+            _ => {
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
+            }
+        }
+
+        match current_runstate {
+            RunState::PreRun
+            | RunState::AwaitingInput
+            | RunState::Examining
+            | RunState::PlayerTurn
+            | RunState::MonsterTurn
+            | RunState::NextLevel => {
+                current_runstate = self.play_turn(current_runstate, |gs| player_input(gs, ctx));
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
@@ -123,17 +376,117 @@ impl GameState for State {
                     }
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected(item) => {
-                        let mut intent = self.ecs.write_storage::<WantsToDrinkPotion>();
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToDrinkPotion { potion: item },
-                            )
-                            .expect("Unable to insert intent");
-                        current_runstate = RunState::AwaitingInput;
+                        current_runstate = use_item(&mut self.ecs, item);
                     }
                 }
             }
+            RunState::ShowTargeting { range, item } => {
+                current_runstate = targeting_input(self, ctx, range, item);
+            }
+            RunState::GameOver => {
+                let result = gui::game_over(&self.ecs, ctx);
+                current_runstate = self.game_over_choice(result);
+            }
+            RunState::MainMenu { .. } => match gui::main_menu(&self.ecs, ctx) {
+                gui::MainMenuResult::NoSelection { selected } => {
+                    current_runstate = RunState::MainMenu {
+                        menu_selection: selected,
+                    };
+                }
+                gui::MainMenuResult::Selected { selected } => match selected {
+                    gui::MainMenuSelection::NewGame => {
+                        self.start_new_game();
+                        current_runstate = *self.ecs.fetch::<RunState>();
+                    }
+                    gui::MainMenuSelection::Continue => {
+                        // Saves are removed once loaded so that a death
+                        // can't be undone by reloading.
+                        match saveload_system::load_game(&mut self.ecs) {
+                            Ok(()) => {
+                                if let Err(e) = saveload_system::delete_save() {
+                                    rltk::console::log(format!(
+                                        "Unable to delete saved game: {}",
+                                        e
+                                    ));
+                                }
+                                self.run_ended = false;
+                                current_runstate = RunState::PreRun;
+                            }
+                            Err(e) => {
+                                rltk::console::log(format!("Unable to load saved game: {}", e))
+                            }
+                        }
+                    }
+                    gui::MainMenuSelection::HighScores => {
+                        current_runstate = RunState::HighScores;
+                    }
+                    gui::MainMenuSelection::Options => {
+                        current_runstate = RunState::Options {
+                            selected: gui::OptionsSelection::MapBuilder,
+                        };
+                    }
+                    gui::MainMenuSelection::Quit => ctx.quit(),
+                },
+            },
+            RunState::MessageHistory { .. } => match gui::show_message_history(&self.ecs, ctx) {
+                gui::MessageHistoryResult::NoResponse { offset } => {
+                    current_runstate = RunState::MessageHistory { offset };
+                }
+                gui::MessageHistoryResult::Close => {
+                    current_runstate = RunState::AwaitingInput;
+                }
+            },
+            RunState::HighScores => {
+                if gui::show_highscores(&self.ecs, ctx) == gui::HighScoresResult::Back {
+                    current_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::HighScores,
+                    };
+                }
+            }
+            RunState::Options { .. } => match gui::options_menu(&self.ecs, ctx) {
+                gui::OptionsResult::NoSelection { selected } => {
+                    current_runstate = RunState::Options { selected };
+                }
+                gui::OptionsResult::Change { selected } => {
+                    let mut config = self.ecs.write_resource::<MapGenConfig>();
+                    match selected {
+                        gui::OptionsSelection::MapBuilder => config.cycle_builder(),
+                        gui::OptionsSelection::ShowGeneration => {
+                            config.show_generation = !config.show_generation
+                        }
+                        gui::OptionsSelection::Back => {}
+                    }
+                }
+                gui::OptionsResult::Back => {
+                    current_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::Options,
+                    };
+                }
+            },
+            RunState::MapGeneration => {
+                let mut history = self.ecs.fetch_mut::<MapGenHistory>();
+                if history.index < history.snapshots.len() {
+                    let snapshot = &history.snapshots[history.index];
+                    let centre = Point::new(snapshot.width / 2, snapshot.height / 2);
+                    camera::render_map(snapshot, &Viewport::centred_on(snapshot, centre), ctx);
+
+                    history.timer += ctx.frame_time_ms;
+                    if history.timer > MAPGEN_FRAME_MS {
+                        history.timer = 0.0;
+                        history.index += 1;
+                    }
+                } else {
+                    current_runstate = RunState::PreRun;
+                }
+            }
+            RunState::SaveGame => {
+                if let Err(e) = saveload_system::save_game(&mut self.ecs) {
+                    rltk::console::log(format!("Unable to save game: {}", e));
+                }
+                current_runstate = RunState::MainMenu {
+                    menu_selection: gui::MainMenuSelection::Continue,
+                };
+            }
             RunState::ShowDropItem => {
                 let result = gui::show_drop_item(self, ctx);
                 match result {
@@ -152,43 +505,176 @@ impl GameState for State {
             }
         }
 
-        {
-            let mut runstate = self.ecs.write_resource::<RunState>();
-            *runstate = current_runstate
-        }
-
-        DamageSystem::delete_the_dead(&mut self.ecs);
+        self.finish_tick(current_runstate);
     }
 }
 
 fn main() {
+    let raws = match RawMaster::load(RAW_FILE) {
+        Ok(raws) => raws,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let map_config = MapGenConfig {
+        builder: parse_arg::<String>("--map-builder"),
+        show_generation: has_flag("--show-mapgen"),
+        width: parse_arg::<i32>("--map-width").unwrap_or(MAPWIDTH),
+        height: parse_arg::<i32>("--map-height").unwrap_or(MAPHEIGHT),
+    };
+    if map_config.width < map_builders::MIN_MAP_WIDTH
+        || map_config.height < map_builders::MIN_MAP_HEIGHT
+    {
+        eprintln!(
+            "Maps must be at least {} by {}",
+            map_builders::MIN_MAP_WIDTH,
+            map_builders::MIN_MAP_HEIGHT
+        );
+        std::process::exit(1);
+    }
+    if let Some(name) = &map_config.builder {
+        if !map_builders::BUILDER_NAMES.contains(&name.as_str()) {
+            eprintln!(
+                "Unknown map builder {}, expected one of: {}",
+                name,
+                map_builders::BUILDER_NAMES.join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(runs) = parse_arg::<u32>("--simulate") {
+        let seed = parse_arg::<u64>("--seed").unwrap_or(0);
+        let max_turns = parse_arg::<i32>("--max-turns").unwrap_or(1000);
+        let map_config = MapGenConfig {
+            show_generation: false,
+            ..map_config
+        };
+        simulation::run_simulations(runs, seed, max_turns, &raws, &map_config);
+        return;
+    }
+
     let context = RltkBuilder::simple80x50().with_title("Rustlike").build();
 
-    let mut gs = State { ecs: World::new() };
+    let seed =
+        parse_arg::<u64>("--seed").unwrap_or_else(|| rltk::RandomNumberGenerator::new().next_u64());
+    // The menu needs a world to sit on top of, so one is built straight away.
+    // Choosing a new game builds another from the same seed.
+    let mut gs = State {
+        ecs: new_game(seed, &raws, &map_config),
+        next_seed: Some(seed),
+        run_ended: false,
+    };
+    // Only the interactive game reads and writes the high score file. The
+    // table is handed on from each run to the next.
+    gs.ecs.insert(HighScoreTable::load());
+    *gs.ecs.write_resource::<RunState>() = RunState::MainMenu {
+        menu_selection: gui::MainMenuSelection::NewGame,
+    };
 
-    register_components(&mut gs.ecs);
+    rltk::main_loop(context, gs);
+}
 
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
+/// Builds a fresh world for a new run, with the first level generated and populated.
+/// It starts with an empty high score table, which the game replaces with the
+/// one read from disk.
+fn new_game(seed: u64, raws: &RawMaster, map_config: &MapGenConfig) -> World {
+    let mut ecs = World::new();
 
-    let map = Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
+    register_components(&mut ecs);
+    ecs.insert(raws.clone());
 
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
+    // Every random roll in the run comes from this one generator, so that a
+    // given seed always produces the same dungeon.
+    let mut rng = RandomNumberGenerator::seeded(seed);
 
-    for room in map.rooms.iter().skip(1) {
-        spawner::populate_room(&mut gs.ecs, room);
-    }
+    let mut builder = map_builders::create_builder(map_config, raws, 1, &mut rng);
+    builder.build_map(&mut rng);
+    ecs.insert(rng);
+    ecs.insert(RunSeed { seed });
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+    let start = builder.get_starting_position();
+    let (player_x, player_y) = (start.x, start.y);
+
+    let player_entity = spawner::player(&mut ecs, player_x, player_y);
+    builder.spawn_entities(&mut ecs);
+    record_generation(&mut ecs, map_config, builder.as_ref());
+    let map = builder.get_map();
+
+    ecs.insert(map);
+    ecs.insert(Point::new(player_x, player_y));
+    ecs.insert(Cursor { x: 0, y: 0 });
+    ecs.insert(player_entity);
+    ecs.insert(map_config.clone());
+    let runstate = state_after_generation(&ecs);
+    ecs.insert(runstate);
+    ecs.insert(GameStats::default());
+    ecs.insert(HighScoreTable::default());
+    ecs.insert(EventQueue::default());
+    ecs.insert(FlowFields::default());
+    ecs.insert(SoundHook::default());
+    ecs.insert(GameLog::new(vec![
+        "Welcome to Rustlike".to_string(),
+        format!("Seed: {}", seed),
+    ]));
 
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(Cursor { x: 0, y: 0 });
-    gs.ecs.insert(player_entity);
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome to Rustlike".to_string()],
+    ecs
+}
+
+/// Keeps the snapshots of a newly generated level if they are to be played back.
+fn record_generation(ecs: &mut World, config: &MapGenConfig, builder: &dyn MapBuilder) {
+    let snapshots = if config.show_generation {
+        builder.get_snapshot_history()
+    } else {
+        Vec::new()
+    };
+    ecs.insert(MapGenHistory {
+        snapshots,
+        ..MapGenHistory::default()
     });
+}
 
-    rltk::main_loop(context, gs);
+/// The state to enter once a level has been generated: playing back its
+/// generation if that was asked for, otherwise straight into the game.
+fn state_after_generation(ecs: &World) -> RunState {
+    if ecs.fetch::<MapGenHistory>().snapshots.is_empty() {
+        RunState::PreRun
+    } else {
+        RunState::MapGeneration
+    }
+}
+
+/// Returns true if the player has been killed, or no longer exists.
+fn is_player_dead(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+
+    match combat_stats.get(*player_entity) {
+        Some(stats) => stats.hp < 1,
+        None => true,
+    }
+}
+
+/// Returns true if a command line flag, such as `--show-mapgen`, was given.
+fn has_flag(flag: &str) -> bool {
+    std::env::args().any(|arg| arg == flag)
+}
+
+/// Reads the value following a command line flag, such as `--seed 1234`.
+fn parse_arg<T: FromStr>(flag: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    let position = args.iter().position(|arg| arg == flag)?;
+
+    match args.get(position + 1).map(|value| value.parse::<T>()) {
+        Some(Ok(value)) => Some(value),
+        _ => {
+            eprintln!("Ignoring {} as it is missing a valid value", flag);
+            None
+        }
+    }
 }
 
 /// Register all the components that we need with the ECS
@@ -201,12 +687,135 @@ fn register_components(ecs: &mut World) {
     ecs.register::<Name>();
     ecs.register::<BlocksTile>();
     ecs.register::<CombatStats>();
+    ecs.register::<Energy>();
+    ecs.register::<Behaviour>();
     ecs.register::<WantsToMelee>();
     ecs.register::<SufferDamage>();
     ecs.register::<Item>();
-    ecs.register::<Potion>();
+    ecs.register::<Consumable>();
+    ecs.register::<Ranged>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Confusion>();
+    ecs.register::<Confused>();
     ecs.register::<InBackpack>();
     ecs.register::<WantsToPickupItem>();
-    ecs.register::<WantsToDrinkPotion>();
+    ecs.register::<WantsToUseItem>();
     ecs.register::<WantsToDropItem>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<DefenceBonus>();
+    ecs.register::<WantsToEquip>();
+    ecs.register::<WantsToUnequip>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> State {
+        let raws = RawMaster::load(RAW_FILE).expect("Unable to load raws");
+        State {
+            ecs: new_game(1, &raws, &MapGenConfig::default()),
+            next_seed: None,
+            // Counted as already recorded, so that dying here doesn't write
+            // to the high score table or the morgue.
+            run_ended: true,
+        }
+    }
+
+    /// The level's tiles and where everything on it was placed.
+    fn level_layout(ecs: &World) -> (Vec<TileType>, Vec<(String, i32, i32)>) {
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let mut placed: Vec<(String, i32, i32)> = (&names, &positions)
+            .join()
+            .map(|(name, pos)| (name.name.clone(), pos.x, pos.y))
+            .collect();
+        placed.sort();
+        (ecs.fetch::<Map>().tiles.clone(), placed)
+    }
+
+    fn kill_player(ecs: &mut World) {
+        let player = *ecs.fetch::<Entity>();
+        ecs.write_storage::<CombatStats>()
+            .get_mut(player)
+            .expect("Player has no stats")
+            .hp = 0;
+    }
+
+    #[test]
+    fn main_menu_can_be_reached_after_death() {
+        let mut gs = test_state();
+        kill_player(&mut gs.ecs);
+
+        gs.finish_tick(RunState::MonsterTurn);
+        assert_eq!(*gs.ecs.fetch::<RunState>(), RunState::GameOver);
+
+        let main_menu = RunState::MainMenu {
+            menu_selection: gui::MainMenuSelection::NewGame,
+        };
+        let next = gs.game_over_choice(gui::GameOverResult::MainMenu);
+        gs.finish_tick(next);
+        assert_eq!(*gs.ecs.fetch::<RunState>(), main_menu);
+
+        // The menu stays put on the following ticks
+        gs.finish_tick(main_menu);
+        assert_eq!(*gs.ecs.fetch::<RunState>(), main_menu);
+    }
+
+    #[test]
+    fn death_while_awaiting_input_skips_the_input() {
+        let mut gs = test_state();
+        kill_player(&mut gs.ecs);
+
+        let next = gs.play_turn(RunState::AwaitingInput, |_| {
+            panic!("A dead player's input was handled")
+        });
+        assert_eq!(next, RunState::GameOver);
+
+        gs.finish_tick(next);
+        assert_eq!(*gs.ecs.fetch::<RunState>(), RunState::GameOver);
+    }
+
+    #[test]
+    fn death_outside_play_does_not_end_the_game() {
+        let mut gs = test_state();
+        kill_player(&mut gs.ecs);
+
+        let options = RunState::Options {
+            selected: gui::OptionsSelection::MapBuilder,
+        };
+        gs.finish_tick(options);
+        assert_eq!(*gs.ecs.fetch::<RunState>(), options);
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_levels() {
+        let raws = RawMaster::load(RAW_FILE).expect("Unable to load raws");
+        let config = MapGenConfig::default();
+
+        for seed in 1..5 {
+            let mut first = State {
+                ecs: new_game(seed, &raws, &config),
+                next_seed: None,
+                run_ended: true,
+            };
+            let mut second = State {
+                ecs: new_game(seed, &raws, &config),
+                next_seed: None,
+                run_ended: true,
+            };
+            assert!(level_layout(&first.ecs) == level_layout(&second.ecs));
+
+            // Later levels come from the same generator
+            first.goto_next_level();
+            second.goto_next_level();
+            assert!(level_layout(&first.ecs) == level_layout(&second.ecs));
+        }
+    }
 }
@@ -1,20 +1,40 @@
 extern crate rltk;
 use super::Rect;
 use rltk::{Algorithm2D, BaseMap, Point};
-use rltk::{Console, RandomNumberGenerator, Rltk, RGB};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPWIDTH * MAPHEIGHT;
+/// The size of levels unless a different size is asked for.
+pub const MAPWIDTH: i32 = 80;
+pub const MAPHEIGHT: i32 = 43;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     WALL,
     FLOOR,
+    DOWNSTAIRS,
+    UPSTAIRS,
+    RUBBLE,
+    SHALLOWWATER,
 }
 
+impl TileType {
+    /// How many times longer than usual it takes to step onto this tile.
+    pub fn move_cost(self) -> f32 {
+        match self {
+            TileType::RUBBLE => 2.0,
+            TileType::SHALLOWWATER => 1.5,
+            _ => 1.0,
+        }
+    }
+}
+
+/// The cost of an orthogonal step between two ordinary tiles. Diagonal steps
+/// cover more ground, so cost a little more.
+const STEP_COST: f32 = 1.0;
+const DIAGONAL_STEP_COST: f32 = 1.45;
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub rooms: Vec<Rect>,
@@ -23,7 +43,11 @@ pub struct Map {
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
+
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
     pub tile_content: Vec<Vec<Entity>>,
+    pub depth: i32,
 }
 
 impl Map {
@@ -68,114 +92,37 @@ impl Map {
         }
     }
 
-    pub fn new_map_rooms_and_corridors() -> Self {
-        let mut map = Map {
-            tiles: vec![TileType::WALL; MAPCOUNT],
-            rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
-        };
-
-        const MAX_ROOMS: i32 = 30;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        'outer: for _ in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    // Skip to the next room
-                    continue 'outer;
-                }
-            }
-            map.apply_room_to_map(&new_room);
-
-            if !map.rooms.is_empty() {
-                let (new_x, new_y) = new_room.center();
-                let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                if rng.range(0, 2) == 1 {
-                    map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                    map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                } else {
-                    map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                }
-            }
-
-            map.rooms.push(new_room);
-        }
-
-        map
+    /// How many times longer than usual it takes to step onto a tile.
+    pub fn move_cost(&self, idx: usize) -> f32 {
+        self.tiles[idx].move_cost()
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::FLOOR;
-            }
-        }
-    }
-
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAPCOUNT {
-                self.tiles[idx as usize] = TileType::FLOOR;
-            }
-        }
-    }
-
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAPCOUNT {
-                self.tiles[idx as usize] = TileType::FLOOR;
-            }
-        }
+    /// What it costs to step from a tile onto one of its neighbours.
+    pub fn step_cost(&self, from_idx: usize, to_idx: usize) -> f32 {
+        let (from_x, from_y) = self.idx_to_xy(from_idx);
+        let (to_x, to_y) = self.idx_to_xy(to_idx);
+        let step_cost = if from_x == to_x || from_y == to_y {
+            STEP_COST
+        } else {
+            DIAGONAL_STEP_COST
+        };
+        step_cost * self.move_cost(to_idx)
     }
-}
-
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            match tile {
-                TileType::FLOOR => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(0.0, 0.5, 0.5);
-                }
-                TileType::WALL => {
-                    glyph = rltk::to_cp437('#');
-                    fg = RGB::from_f32(0.0, 1.0, 0.0);
-                }
-            }
 
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
-            }
-            ctx.set(x, y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
-        }
-
-        x += 1;
-        if x > 79 {
-            x = 0;
-            y += 1;
+    /// Creates a map of solid wall for the given dungeon depth, ready for a
+    /// map builder to carve out.
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let map_count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::WALL; map_count],
+            rooms: Vec::new(),
+            width,
+            height,
+            revealed_tiles: vec![false; map_count],
+            visible_tiles: vec![false; map_count],
+            blocked: vec![false; map_count],
+            tile_content: vec![Vec::new(); map_count],
+            depth: new_depth,
         }
     }
 }
@@ -191,47 +138,112 @@ impl BaseMap for Map {
         self.tiles[idx] == TileType::WALL
     }
 
+    /// The tiles that can be stepped onto from a tile, in all eight
+    /// directions, along with what it costs to step onto each.
     fn get_available_exits(&self, idx: usize) -> Vec<(usize, f32)> {
         let mut exits: Vec<(usize, f32)> = Vec::new();
         let (x, y) = self.idx_to_xy(idx);
-        let w = self.width as usize;
 
-        // Cardinal Directions
-        // Left
-        if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
-        };
-        // Right
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
-        };
-        // Up
-        if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
-        };
-        // Down
-        if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
-        };
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx == 0 && dy == 0) || !self.is_exit_valid(x + dx, y + dy) {
+                    continue;
+                }
 
-        // Diagonals
-        // Up Left
-        if self.is_exit_valid(x - 1, y - 1) {
-            exits.push((idx - w, 1.45))
-        };
-        // Up Right
-        if self.is_exit_valid(x + 1, y - 1) {
-            exits.push((idx - w, 1.45))
-        };
-        // Down Left
-        if self.is_exit_valid(x - 1, y + 1) {
-            exits.push((idx + w, 1.45))
-        };
-        // Down Right
-        if self.is_exit_valid(x + 1, y + 1) {
-            exits.push((idx + w, 1.45))
-        };
+                let exit = self.xy_idx(x + dx, y + dy);
+                exits.push((exit, self.step_cost(idx, exit)));
+            }
+        }
 
         exits
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10x10 map of floor surrounded by wall.
+    fn open_map() -> Map {
+        let mut map = Map::new(1, 10, 10);
+        for y in 1..9 {
+            for x in 1..9 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::FLOOR;
+            }
+        }
+        map.populate_blocked();
+        map
+    }
+
+    fn set_tile(map: &mut Map, x: i32, y: i32, tile: TileType) {
+        let idx = map.xy_idx(x, y);
+        map.tiles[idx] = tile;
+    }
+
+    #[test]
+    fn exits_lead_in_all_eight_directions() {
+        let map = open_map();
+        let mut exits = map.get_available_exits(map.xy_idx(4, 4));
+        exits.sort_by_key(|(idx, _)| *idx);
+
+        let mut expected = Vec::new();
+        for (x, y) in [
+            (3, 3),
+            (4, 3),
+            (5, 3),
+            (3, 4),
+            (5, 4),
+            (3, 5),
+            (4, 5),
+            (5, 5),
+        ] {
+            let cost = if x == 4 || y == 4 {
+                STEP_COST
+            } else {
+                DIAGONAL_STEP_COST
+            };
+            expected.push((map.xy_idx(x, y), cost));
+        }
+
+        assert_eq!(exits, expected);
+    }
+
+    #[test]
+    fn exits_skip_walls_and_blocked_tiles() {
+        let mut map = open_map();
+        set_tile(&mut map, 5, 4, TileType::WALL);
+        map.populate_blocked();
+        let occupied = map.xy_idx(3, 3);
+        map.blocked[occupied] = true;
+
+        let exits: Vec<usize> = map
+            .get_available_exits(map.xy_idx(4, 4))
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect();
+
+        assert_eq!(exits.len(), 6);
+        assert!(!exits.contains(&map.xy_idx(5, 4)));
+        assert!(!exits.contains(&occupied));
+    }
+
+    #[test]
+    fn exits_cost_more_on_rough_ground() {
+        let mut map = open_map();
+        set_tile(&mut map, 5, 4, TileType::RUBBLE);
+        set_tile(&mut map, 5, 5, TileType::SHALLOWWATER);
+
+        let exits = map.get_available_exits(map.xy_idx(4, 4));
+        let cost_to = |x, y| {
+            exits
+                .iter()
+                .find(|(idx, _)| *idx == map.xy_idx(x, y))
+                .map(|(_, cost)| *cost)
+        };
+
+        assert_eq!(cost_to(5, 4), Some(STEP_COST * 2.0));
+        assert_eq!(cost_to(5, 5), Some(DIAGONAL_STEP_COST * 1.5));
+        assert_eq!(cost_to(3, 4), Some(STEP_COST));
+    }
+}
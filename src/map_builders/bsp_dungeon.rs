@@ -0,0 +1,103 @@
+use super::RandomNumberGenerator;
+use super::{apply_room_to_map, connect_rooms, BuilderMap, InitialMapBuilder, Rect};
+
+/// The smallest region the map is split into, including its walls.
+const MIN_LEAF_SIZE: i32 = 8;
+/// Regions larger than this in either direction are always split further.
+const MAX_LEAF_SIZE: i32 = 18;
+const MIN_ROOM_SIZE: i32 = 4;
+
+/// Recursively splits the map in two until the regions are small enough, then
+/// places one room in each region. As the regions never overlap, no rooms are
+/// thrown away, and rooms are joined in the order they were split so that
+/// corridors run between neighbours.
+pub struct BspDungeonBuilder {}
+
+impl BspDungeonBuilder {
+    pub fn new() -> Box<BspDungeonBuilder> {
+        Box::new(BspDungeonBuilder {})
+    }
+}
+
+impl InitialMapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let mut leaves: Vec<Rect> = Vec::new();
+        let whole_map = Rect::new(0, 0, build_data.map.width - 1, build_data.map.height - 1);
+        split_region(whole_map, rng, &mut leaves);
+
+        for leaf in leaves.iter() {
+            let map = &mut build_data.map;
+            let new_room = room_in_leaf(leaf, rng);
+            apply_room_to_map(map, &new_room);
+
+            if let Some(prev_room) = map.rooms.last().copied() {
+                connect_rooms(map, &prev_room, &new_room, rng);
+            }
+
+            map.rooms.push(new_room);
+            build_data.take_snapshot();
+        }
+    }
+}
+
+/// Splits a region in two along its longer side, repeating on each half until
+/// the pieces can't be split any further or are randomly left whole.
+fn split_region(region: Rect, rng: &mut RandomNumberGenerator, leaves: &mut Vec<Rect>) {
+    let width = region.x2 - region.x1;
+    let height = region.y2 - region.y1;
+    let can_split_x = width >= MIN_LEAF_SIZE * 2;
+    let can_split_y = height >= MIN_LEAF_SIZE * 2;
+    let small_enough = width <= MAX_LEAF_SIZE && height <= MAX_LEAF_SIZE;
+
+    if (!can_split_x && !can_split_y) || (small_enough && rng.roll_dice(1, 4) == 1) {
+        leaves.push(region);
+        return;
+    }
+
+    let split_x = if can_split_x && can_split_y {
+        width > height || (width == height && rng.roll_dice(1, 2) == 1)
+    } else {
+        can_split_x
+    };
+
+    // Neighbouring regions share their dividing line, which rooms never use
+    if split_x {
+        let split = region.x1 + rng.range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+        split_region(
+            Rect::new(region.x1, region.y1, split - region.x1, height),
+            rng,
+            leaves,
+        );
+        split_region(
+            Rect::new(split, region.y1, region.x2 - split, height),
+            rng,
+            leaves,
+        );
+    } else {
+        let split = region.y1 + rng.range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+        split_region(
+            Rect::new(region.x1, region.y1, width, split - region.y1),
+            rng,
+            leaves,
+        );
+        split_region(
+            Rect::new(region.x1, split, width, region.y2 - split),
+            rng,
+            leaves,
+        );
+    }
+}
+
+/// Picks a randomly sized room that fits inside a leaf, leaving a wall
+/// between it and the leaf's far edges.
+fn room_in_leaf(leaf: &Rect, rng: &mut RandomNumberGenerator) -> Rect {
+    let leaf_width = leaf.x2 - leaf.x1;
+    let leaf_height = leaf.y2 - leaf.y1;
+
+    let w = rng.range(MIN_ROOM_SIZE, leaf_width);
+    let h = rng.range(MIN_ROOM_SIZE, leaf_height);
+    let x = leaf.x1 + rng.range(0, leaf_width - w);
+    let y = leaf.y1 + rng.range(0, leaf_height - h);
+
+    Rect::new(x, y, w, h)
+}
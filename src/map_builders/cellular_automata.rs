@@ -0,0 +1,63 @@
+use super::RandomNumberGenerator;
+use super::{BuilderMap, InitialMapBuilder, TileType};
+
+/// How many times the smoothing rules are applied to the initial noise.
+const ITERATIONS: i32 = 15;
+
+/// Grows natural looking caves by starting from random noise and repeatedly
+/// turning each tile into wall or floor depending on its neighbours. The
+/// result has no rooms and may contain pockets that can't be reached, so it
+/// should be followed by steps that pick a start and cull unreachable areas.
+pub struct CellularAutomataBuilder {}
+
+impl CellularAutomataBuilder {
+    pub fn new() -> Box<CellularAutomataBuilder> {
+        Box::new(CellularAutomataBuilder {})
+    }
+}
+
+impl InitialMapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        // Start from noise, leaving the edges of the map solid
+        let map = &mut build_data.map;
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
+                let idx = map.xy_idx(x, y);
+                if rng.roll_dice(1, 100) > 55 {
+                    map.tiles[idx] = TileType::FLOOR;
+                }
+            }
+        }
+        build_data.take_snapshot();
+
+        // Tiles crowded by walls, or with none around them at all, become
+        // walls. Everything else opens up.
+        for _i in 0..ITERATIONS {
+            let map = &mut build_data.map;
+            let mut new_tiles = map.tiles.clone();
+            for y in 1..map.height - 1 {
+                for x in 1..map.width - 1 {
+                    let mut neighbours = 0;
+                    for dy in -1..=1 {
+                        for dx in -1..=1 {
+                            if (dx != 0 || dy != 0)
+                                && map.tiles[map.xy_idx(x + dx, y + dy)] == TileType::WALL
+                            {
+                                neighbours += 1;
+                            }
+                        }
+                    }
+
+                    let idx = map.xy_idx(x, y);
+                    if neighbours > 4 || neighbours == 0 {
+                        new_tiles[idx] = TileType::WALL;
+                    } else {
+                        new_tiles[idx] = TileType::FLOOR;
+                    }
+                }
+            }
+            map.tiles = new_tiles;
+            build_data.take_snapshot();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use super::RandomNumberGenerator;
+use super::{Map, Rect, TileType};
+use rltk::{CellularDistanceFunction, FastNoise, NoiseType};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, VecDeque};
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::FLOOR;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.tiles.len() {
+            map.tiles[idx] = TileType::FLOOR;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.tiles.len() {
+            map.tiles[idx] = TileType::FLOOR;
+        }
+    }
+}
+
+/// Joins the centres of two rooms with an L-shaped corridor, randomly
+/// choosing which way round the bend goes.
+pub fn connect_rooms(map: &mut Map, from: &Rect, to: &Rect, rng: &mut RandomNumberGenerator) {
+    let (new_x, new_y) = to.center();
+    let (prev_x, prev_y) = from.center();
+    if rng.range(0, 2) == 1 {
+        apply_horizontal_tunnel(map, prev_x, new_x, prev_y);
+        apply_vertical_tunnel(map, prev_y, new_y, new_x);
+    } else {
+        apply_horizontal_tunnel(map, prev_x, new_x, new_y);
+        apply_vertical_tunnel(map, prev_y, new_y, prev_x);
+    }
+}
+
+/// The number of steps needed to walk from `start_idx` to every tile, or
+/// `None` for tiles that can't be reached.
+pub fn walking_distances(map: &Map, start_idx: usize) -> Vec<Option<i32>> {
+    let mut distance: Vec<Option<i32>> = vec![None; map.tiles.len()];
+    let mut open_list: VecDeque<usize> = VecDeque::new();
+    distance[start_idx] = Some(0);
+    open_list.push_back(start_idx);
+
+    while let Some(idx) = open_list.pop_front() {
+        let (x, y) = map.idx_to_xy(idx);
+        let step = distance[idx].unwrap_or(0) + 1;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if !map.is_in_bounds(x + dx, y + dy) {
+                    continue;
+                }
+                let exit = map.xy_idx(x + dx, y + dy);
+                if distance[exit].is_some() || map.tiles[exit] == TileType::WALL {
+                    continue;
+                }
+                distance[exit] = Some(step);
+                open_list.push_back(exit);
+            }
+        }
+    }
+
+    distance
+}
+
+/// Splits the floor of a map into regions using cellular noise, so that maps
+/// without rooms can still be populated a region at a time.
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+) -> Vec<Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    let mut noise = FastNoise::seeded(rng.next_u64());
+    noise.set_noise_type(NoiseType::Cellular);
+    noise.set_frequency(0.08);
+    noise.set_cellular_distance_function(CellularDistanceFunction::Manhattan);
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::FLOOR {
+                let cell_value = (noise.get_noise(x as f32, y as f32) * 10240.0) as i32;
+                noise_areas.entry(cell_value).or_default().push(idx);
+            }
+        }
+    }
+
+    noise_areas.into_values().collect()
+}
@@ -0,0 +1,28 @@
+use super::RandomNumberGenerator;
+use super::{walking_distances, BuilderMap, MetaMapBuilder, TileType};
+
+/// Fills in every part of the map the player can't walk to from where they start.
+pub struct CullUnreachable {}
+
+impl CullUnreachable {
+    pub fn new() -> Box<CullUnreachable> {
+        Box::new(CullUnreachable {})
+    }
+}
+
+impl MetaMapBuilder for CullUnreachable {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start = build_data
+            .starting_position
+            .as_ref()
+            .expect("Culling unreachable areas needs a starting position");
+        let start_idx = build_data.map.xy_idx(start.x, start.y);
+
+        let distances = walking_distances(&build_data.map, start_idx);
+        for (tile, distance) in build_data.map.tiles.iter_mut().zip(distances.iter()) {
+            if distance.is_none() {
+                *tile = TileType::WALL;
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use super::{
+    spawner, Map, Position, RandomNumberGenerator, RawMaster, Rect, TileType, Vault, MAPHEIGHT,
+    MAPWIDTH,
+};
+use specs::prelude::*;
+mod bsp_dungeon;
+mod cellular_automata;
+mod common;
+mod cull_unreachable;
+mod prefab_vault;
+mod simple_map;
+mod spawn_regions;
+mod stairs;
+mod starting_position;
+mod terrain;
+use bsp_dungeon::BspDungeonBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use common::*;
+use cull_unreachable::CullUnreachable;
+use prefab_vault::PrefabVault;
+use simple_map::SimpleMapBuilder;
+use spawn_regions::{RoomBasedSpawner, VoronoiSpawning};
+use stairs::{DistantExit, RoomBasedStairs};
+use starting_position::{AreaStartingPosition, RoomBasedStartingPosition};
+use terrain::ScatterTerrain;
+
+/// The names of the generators that can be asked for with `--map-builder`.
+pub const BUILDER_NAMES: [&str; 3] = ["simple", "bsp", "cellular"];
+
+/// The percentage chance of a level containing a vault.
+const VAULT_CHANCE: i32 = 50;
+
+/// The smallest map every generator can cope with.
+pub const MIN_MAP_WIDTH: i32 = 40;
+pub const MIN_MAP_HEIGHT: i32 = 25;
+
+/// How levels are generated for a run, kept as a resource so every level is
+/// built the same way.
+#[derive(Clone)]
+pub struct MapGenConfig {
+    /// The generator to use, or `None` to pick one at random for each level.
+    pub builder: Option<String>,
+    /// Whether to play back each level being generated before it starts.
+    pub show_generation: bool,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Default for MapGenConfig {
+    fn default() -> MapGenConfig {
+        MapGenConfig {
+            builder: None,
+            show_generation: false,
+            width: MAPWIDTH,
+            height: MAPHEIGHT,
+        }
+    }
+}
+
+impl MapGenConfig {
+    /// Moves on to the next generator in `BUILDER_NAMES`, going back to
+    /// picking at random after the last one.
+    pub fn cycle_builder(&mut self) {
+        let next = match &self.builder {
+            None => 0,
+            Some(name) => match BUILDER_NAMES.iter().position(|n| n == name) {
+                Some(i) => i + 1,
+                None => BUILDER_NAMES.len(),
+            },
+        };
+        self.builder = BUILDER_NAMES.get(next).map(|name| name.to_string());
+    }
+}
+
+/// The snapshots taken while generating the current level, and how far
+/// through playing them back we are.
+#[derive(Default)]
+pub struct MapGenHistory {
+    pub snapshots: Vec<Map>,
+    pub index: usize,
+    pub timer: f32,
+}
+
+/// A finished level generator, as used by the game. Builders are created for
+/// a single depth; once built they know where the player starts, how to fill
+/// the level with monsters and items, and how the map looked at each step of
+/// its generation.
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn spawn_entities(&mut self, ecs: &mut World);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn get_snapshot_history(&self) -> Vec<Map>;
+}
+
+/// The level as it is passed along a chain of builders, each of which can
+/// change the map or fill in more of the details.
+pub struct BuilderMap {
+    pub map: Map,
+    pub starting_position: Option<Position>,
+    /// Areas of the map, as lists of tile indices, to fill with monsters and items.
+    pub spawn_regions: Vec<Vec<usize>>,
+    /// Monsters and items that must be spawned on particular tiles.
+    pub spawn_list: Vec<(usize, String)>,
+    pub history: Vec<Map>,
+}
+
+impl BuilderMap {
+    /// Records the map as it is now, fully revealed, so that the generation
+    /// can be played back.
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for revealed in snapshot.revealed_tiles.iter_mut() {
+            *revealed = true;
+        }
+        self.history.push(snapshot);
+    }
+}
+
+/// A generator that carves the initial layout out of a solid map.
+pub trait InitialMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+/// A step that works on a map that has already been generated, such as
+/// choosing where the stairs go.
+pub trait MetaMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+/// Runs an initial generator followed by any number of post-processing steps.
+pub struct BuilderChain {
+    starter: Box<dyn InitialMapBuilder>,
+    builders: Vec<Box<dyn MetaMapBuilder>>,
+    depth: i32,
+    build_data: BuilderMap,
+}
+
+impl BuilderChain {
+    pub fn new(
+        new_depth: i32,
+        config: &MapGenConfig,
+        starter: Box<dyn InitialMapBuilder>,
+    ) -> BuilderChain {
+        BuilderChain {
+            starter,
+            builders: Vec::new(),
+            depth: new_depth,
+            build_data: BuilderMap {
+                map: Map::new(new_depth, config.width, config.height),
+                starting_position: None,
+                spawn_regions: Vec::new(),
+                spawn_list: Vec::new(),
+                history: Vec::new(),
+            },
+        }
+    }
+
+    pub fn with(mut self, builder: Box<dyn MetaMapBuilder>) -> BuilderChain {
+        self.builders.push(builder);
+        self
+    }
+}
+
+impl MapBuilder for BuilderChain {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.starter.build_map(rng, &mut self.build_data);
+        self.build_data.take_snapshot();
+
+        for builder in self.builders.iter_mut() {
+            builder.build_map(rng, &mut self.build_data);
+            self.build_data.take_snapshot();
+        }
+
+        // Below the first level the player arrives on the stairs up
+        if self.depth > 1 {
+            let start = self.get_starting_position();
+            let start_idx = self.build_data.map.xy_idx(start.x, start.y);
+            self.build_data.map.tiles[start_idx] = TileType::UPSTAIRS;
+        }
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        // Leave the area the player starts in empty
+        let start = self.get_starting_position();
+        let start_idx = self.build_data.map.xy_idx(start.x, start.y);
+        for region in self.build_data.spawn_regions.iter() {
+            if !region.contains(&start_idx) {
+                spawner::spawn_region(ecs, &self.build_data.map, region, self.depth);
+            }
+        }
+
+        for (idx, name) in self.build_data.spawn_list.iter() {
+            let (x, y) = self.build_data.map.idx_to_xy(*idx);
+            spawner::spawn_named(ecs, name, x, y);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.build_data.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.build_data
+            .starting_position
+            .clone()
+            .expect("Map builder chain did not choose a starting position")
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.build_data.history.clone()
+    }
+}
+
+/// Creates the builder with the given name, or `None` if there is no such
+/// builder. See `BUILDER_NAMES`.
+fn named_builder(name: &str, new_depth: i32, config: &MapGenConfig) -> Option<BuilderChain> {
+    match name {
+        "simple" => Some(room_builder(new_depth, config, SimpleMapBuilder::new())),
+        "bsp" => Some(room_builder(new_depth, config, BspDungeonBuilder::new())),
+        "cellular" => Some(cave_builder(new_depth, config)),
+        _ => None,
+    }
+}
+
+/// Picks a generator at random, and sometimes mixes up how the stairs are placed.
+fn random_builder(
+    new_depth: i32,
+    config: &MapGenConfig,
+    rng: &mut RandomNumberGenerator,
+) -> BuilderChain {
+    match rng.roll_dice(1, 3) {
+        1 => cave_builder(new_depth, config),
+        roll => {
+            let starter: Box<dyn InitialMapBuilder> = if roll == 2 {
+                SimpleMapBuilder::new()
+            } else {
+                BspDungeonBuilder::new()
+            };
+            let chain = BuilderChain::new(new_depth, config, starter)
+                .with(RoomBasedStartingPosition::new())
+                .with(RoomBasedSpawner::new());
+
+            if rng.roll_dice(1, 2) == 1 {
+                chain.with(RoomBasedStairs::new())
+            } else {
+                chain.with(DistantExit::new())
+            }
+        }
+    }
+}
+
+/// Creates the builder asked for by the config, otherwise one at random, and
+/// gives it a chance of including one of the vaults allowed at this depth.
+/// Every level is finished off with some rough ground.
+pub fn create_builder(
+    config: &MapGenConfig,
+    raws: &RawMaster,
+    new_depth: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    let mut chain = match config
+        .builder
+        .as_ref()
+        .and_then(|name| named_builder(name, new_depth, config))
+    {
+        Some(chain) => chain,
+        None => random_builder(new_depth, config, rng),
+    };
+
+    if rng.roll_dice(1, 100) <= VAULT_CHANCE {
+        let vault = raws
+            .vault_table(new_depth)
+            .roll(rng)
+            .and_then(|name| raws.vault(&name).cloned());
+        if let Some(vault) = vault {
+            chain = chain.with(PrefabVault::new(vault));
+        }
+    }
+
+    Box::new(chain.with(ScatterTerrain::new()))
+}
+
+fn room_builder(
+    new_depth: i32,
+    config: &MapGenConfig,
+    starter: Box<dyn InitialMapBuilder>,
+) -> BuilderChain {
+    BuilderChain::new(new_depth, config, starter)
+        .with(RoomBasedStartingPosition::new())
+        .with(RoomBasedSpawner::new())
+        .with(RoomBasedStairs::new())
+}
+
+fn cave_builder(new_depth: i32, config: &MapGenConfig) -> BuilderChain {
+    BuilderChain::new(new_depth, config, CellularAutomataBuilder::new())
+        .with(AreaStartingPosition::new())
+        .with(CullUnreachable::new())
+        .with(VoronoiSpawning::new())
+        .with(DistantExit::new())
+}
@@ -0,0 +1,78 @@
+use super::RandomNumberGenerator;
+use super::{BuilderMap, MetaMapBuilder, TileType, Vault};
+
+/// Stamps a hand drawn vault into the map, somewhere it fits entirely on open
+/// floor with room to walk around it, so it can't cut the level in two. If
+/// there is nowhere big enough the map is left alone.
+pub struct PrefabVault {
+    vault: Vault,
+}
+
+impl PrefabVault {
+    pub fn new(vault: Vault) -> Box<PrefabVault> {
+        Box::new(PrefabVault { vault })
+    }
+
+    /// Whether the vault, plus a one tile margin, can go with its top left
+    /// corner at (x, y) without covering anything but plain floor.
+    fn fits(&self, build_data: &BuilderMap, x: i32, y: i32) -> bool {
+        let map = &build_data.map;
+        let start_idx = build_data
+            .starting_position
+            .as_ref()
+            .map(|start| map.xy_idx(start.x, start.y));
+
+        for vault_y in y - 1..=y + self.vault.height {
+            for vault_x in x - 1..=x + self.vault.width {
+                if !map.is_in_bounds(vault_x, vault_y) {
+                    return false;
+                }
+                let idx = map.xy_idx(vault_x, vault_y);
+                if map.tiles[idx] != TileType::FLOOR || Some(idx) == start_idx {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl MetaMapBuilder for PrefabVault {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let mut candidates: Vec<(i32, i32)> = Vec::new();
+        for y in 0..build_data.map.height - self.vault.height {
+            for x in 0..build_data.map.width - self.vault.width {
+                if self.fits(build_data, x, y) {
+                    candidates.push((x, y));
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            return;
+        }
+        let (x, y) = candidates[(rng.roll_dice(1, candidates.len() as i32) - 1) as usize];
+
+        let mut footprint: Vec<usize> = Vec::new();
+        for vault_y in 0..self.vault.height {
+            for vault_x in 0..self.vault.width {
+                let idx = build_data.map.xy_idx(x + vault_x, y + vault_y);
+                footprint.push(idx);
+
+                let cell = &self.vault.cells[(vault_y * self.vault.width + vault_x) as usize];
+                if let Some(cell) = cell {
+                    build_data.map.tiles[idx] = cell.tile;
+                    if let Some(spawn) = &cell.spawn {
+                        build_data.spawn_list.push((idx, spawn.clone()));
+                    }
+                }
+            }
+        }
+
+        // The vault brings its own monsters and items
+        for region in build_data.spawn_regions.iter_mut() {
+            region.retain(|idx| !footprint.contains(idx));
+        }
+    }
+}
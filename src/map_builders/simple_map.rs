@@ -0,0 +1,43 @@
+use super::RandomNumberGenerator;
+use super::{apply_room_to_map, connect_rooms, BuilderMap, InitialMapBuilder, Rect};
+
+/// Scatters randomly sized rooms over the map, discarding any that overlap,
+/// and joins each room to the previous one with a corridor.
+pub struct SimpleMapBuilder {}
+
+impl SimpleMapBuilder {
+    pub fn new() -> Box<SimpleMapBuilder> {
+        Box::new(SimpleMapBuilder {})
+    }
+}
+
+impl InitialMapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        const MAX_ROOMS: i32 = 30;
+        const MIN_SIZE: i32 = 6;
+        const MAX_SIZE: i32 = 10;
+
+        'outer: for _ in 0..MAX_ROOMS {
+            let map = &mut build_data.map;
+            let w = rng.range(MIN_SIZE, MAX_SIZE);
+            let h = rng.range(MIN_SIZE, MAX_SIZE);
+            let x = rng.roll_dice(1, map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, map.height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+            for other_room in map.rooms.iter() {
+                if new_room.intersect(other_room) {
+                    // Skip to the next room
+                    continue 'outer;
+                }
+            }
+            apply_room_to_map(map, &new_room);
+
+            if let Some(prev_room) = map.rooms.last().copied() {
+                connect_rooms(map, &prev_room, &new_room, rng);
+            }
+
+            map.rooms.push(new_room);
+            build_data.take_snapshot();
+        }
+    }
+}
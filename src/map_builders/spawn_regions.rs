@@ -0,0 +1,42 @@
+use super::RandomNumberGenerator;
+use super::{generate_voronoi_spawn_regions, BuilderMap, MetaMapBuilder};
+
+/// Fills each room with monsters and items.
+pub struct RoomBasedSpawner {}
+
+impl RoomBasedSpawner {
+    pub fn new() -> Box<RoomBasedSpawner> {
+        Box::new(RoomBasedSpawner {})
+    }
+}
+
+impl MetaMapBuilder for RoomBasedSpawner {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        for room in build_data.map.rooms.iter() {
+            let mut region: Vec<usize> = Vec::new();
+            for y in room.y1 + 1..=room.y2 {
+                for x in room.x1 + 1..=room.x2 {
+                    region.push(build_data.map.xy_idx(x, y));
+                }
+            }
+            build_data.spawn_regions.push(region);
+        }
+    }
+}
+
+/// Splits the floor into regions with cellular noise and fills each of them
+/// with monsters and items, for maps without rooms.
+pub struct VoronoiSpawning {}
+
+impl VoronoiSpawning {
+    pub fn new() -> Box<VoronoiSpawning> {
+        Box::new(VoronoiSpawning {})
+    }
+}
+
+impl MetaMapBuilder for VoronoiSpawning {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let mut regions = generate_voronoi_spawn_regions(&build_data.map, rng);
+        build_data.spawn_regions.append(&mut regions);
+    }
+}
@@ -0,0 +1,53 @@
+use super::RandomNumberGenerator;
+use super::{walking_distances, BuilderMap, MetaMapBuilder, TileType};
+
+/// Places the stairs down in the centre of the last room.
+pub struct RoomBasedStairs {}
+
+impl RoomBasedStairs {
+    pub fn new() -> Box<RoomBasedStairs> {
+        Box::new(RoomBasedStairs {})
+    }
+}
+
+impl MetaMapBuilder for RoomBasedStairs {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let (x, y) = build_data
+            .map
+            .rooms
+            .last()
+            .expect("Room based stairs need a map with rooms")
+            .center();
+        let stairs_idx = build_data.map.xy_idx(x, y);
+        build_data.map.tiles[stairs_idx] = TileType::DOWNSTAIRS;
+    }
+}
+
+/// Places the stairs down on the tile that takes the longest to walk to from
+/// where the player starts.
+pub struct DistantExit {}
+
+impl DistantExit {
+    pub fn new() -> Box<DistantExit> {
+        Box::new(DistantExit {})
+    }
+}
+
+impl MetaMapBuilder for DistantExit {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start = build_data
+            .starting_position
+            .as_ref()
+            .expect("A distant exit needs a starting position");
+        let start_idx = build_data.map.xy_idx(start.x, start.y);
+
+        let distances = walking_distances(&build_data.map, start_idx);
+        let exit_idx = distances
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, distance)| distance.map(|d| (idx, d)))
+            .max_by_key(|(_, distance)| *distance)
+            .map_or(start_idx, |(idx, _)| idx);
+        build_data.map.tiles[exit_idx] = TileType::DOWNSTAIRS;
+    }
+}
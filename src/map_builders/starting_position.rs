@@ -0,0 +1,52 @@
+use super::RandomNumberGenerator;
+use super::{BuilderMap, MetaMapBuilder, Position, TileType};
+
+/// Starts the player in the centre of the first room.
+pub struct RoomBasedStartingPosition {}
+
+impl RoomBasedStartingPosition {
+    pub fn new() -> Box<RoomBasedStartingPosition> {
+        Box::new(RoomBasedStartingPosition {})
+    }
+}
+
+impl MetaMapBuilder for RoomBasedStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let (x, y) = build_data
+            .map
+            .rooms
+            .first()
+            .expect("Room based starting position needs a map with rooms")
+            .center();
+        build_data.starting_position = Some(Position { x, y });
+    }
+}
+
+/// Starts the player on the floor tile closest to the middle of the map, for
+/// maps without rooms.
+pub struct AreaStartingPosition {}
+
+impl AreaStartingPosition {
+    pub fn new() -> Box<AreaStartingPosition> {
+        Box::new(AreaStartingPosition {})
+    }
+}
+
+impl MetaMapBuilder for AreaStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &mut build_data.map;
+        let (centre_x, centre_y) = (map.width / 2, map.height / 2);
+        let start_idx = (0..map.tiles.len())
+            .filter(|idx| map.tiles[*idx] == TileType::FLOOR)
+            .min_by_key(|idx| {
+                let (x, y) = map.idx_to_xy(*idx);
+                (x - centre_x).pow(2) + (y - centre_y).pow(2)
+            })
+            .unwrap_or_else(|| map.xy_idx(centre_x, centre_y));
+
+        // Make sure there is somewhere to stand, even on a map with no floor
+        map.tiles[start_idx] = TileType::FLOOR;
+        let (x, y) = map.idx_to_xy(start_idx);
+        build_data.starting_position = Some(Position { x, y });
+    }
+}
@@ -0,0 +1,56 @@
+use super::RandomNumberGenerator;
+use super::{BuilderMap, MetaMapBuilder, TileType};
+
+/// The most patches of rough ground placed on a level.
+const MAX_PATCHES: i32 = 6;
+
+/// The largest radius, in tiles, of a patch.
+const MAX_PATCH_RADIUS: i32 = 3;
+
+/// Scatters patches of rubble and shallow water over the floor, which slow
+/// down anything walking through them. Runs last so that earlier steps only
+/// ever see plain floor.
+pub struct ScatterTerrain {}
+
+impl ScatterTerrain {
+    pub fn new() -> Box<ScatterTerrain> {
+        Box::new(ScatterTerrain {})
+    }
+}
+
+impl MetaMapBuilder for ScatterTerrain {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &mut build_data.map;
+        let floor: Vec<usize> = (0..map.tiles.len())
+            .filter(|idx| map.tiles[*idx] == TileType::FLOOR)
+            .collect();
+        if floor.is_empty() {
+            return;
+        }
+
+        for _ in 0..rng.roll_dice(1, MAX_PATCHES) {
+            let centre = floor[rng.range(0, floor.len() as i32) as usize];
+            let (centre_x, centre_y) = map.idx_to_xy(centre);
+            let radius = rng.range(1, MAX_PATCH_RADIUS + 1);
+            let tile = if rng.roll_dice(1, 2) == 1 {
+                TileType::RUBBLE
+            } else {
+                TileType::SHALLOWWATER
+            };
+
+            for y in centre_y - radius..=centre_y + radius {
+                for x in centre_x - radius..=centre_x + radius {
+                    let (dx, dy) = (x - centre_x, y - centre_y);
+                    if !map.is_in_bounds(x, y) || dx * dx + dy * dy > radius * radius {
+                        continue;
+                    }
+
+                    let idx = map.xy_idx(x, y);
+                    if map.tiles[idx] == TileType::FLOOR {
+                        map.tiles[idx] = tile;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
 extern crate specs;
-use super::{CombatStats, GameLog, Name, SufferDamage, WantsToMelee};
+use super::{
+    scheduler, CombatStats, DefenceBonus, Energy, Equipped, EventQueue, GameEvent, MeleePowerBonus,
+    Name, SufferDamage, WantsToMelee,
+};
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
@@ -8,37 +11,67 @@ impl<'a> System<'a> for MeleeCombatSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         Entities<'a>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EventQueue>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Energy>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenceBonus>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut suffer_damage) = data;
+        let (
+            entities,
+            mut events,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut suffer_damage,
+            mut energies,
+            equipped,
+            power_bonuses,
+            defence_bonuses,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, _name, stats) in
             (&entities, &mut wants_melee, &names, &combat_stats).join()
         {
+            scheduler::spend_energy(&mut energies, entity, scheduler::ACTION_COST);
+
             // Dead people can't attack
             if stats.hp > 0 {
-                let target_stats = combat_stats.get(wants_melee.target).unwrap();
+                // The target may have been removed since the attack was decided on
+                let target_stats = match combat_stats.get(wants_melee.target) {
+                    Some(target_stats) => target_stats,
+                    None => continue,
+                };
                 // Don't attack dead people
                 if target_stats.hp > 0 {
-                    let target_name = names.get(wants_melee.target).unwrap();
-                    let damage = i32::max(0, stats.power - target_stats.defence);
+                    // Equipped gear adds to the attacker's power and the
+                    // target's defence
+                    let power = stats.power
+                        + (&equipped, &power_bonuses)
+                            .join()
+                            .filter(|(equipped, _)| equipped.owner == entity)
+                            .map(|(_, bonus)| bonus.power)
+                            .sum::<i32>();
+                    let defence = target_stats.defence
+                        + (&equipped, &defence_bonuses)
+                            .join()
+                            .filter(|(equipped, _)| equipped.owner == wants_melee.target)
+                            .map(|(_, bonus)| bonus.defence)
+                            .sum::<i32>();
+                    let damage = i32::max(0, power - defence);
 
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{} is unable to hurt {}",
-                            &name.name, &target_name.name
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {} for {} damage",
-                            &name.name, &target_name.name, damage
-                        ));
+                    events.emit(GameEvent::Attacked {
+                        attacker: entity,
+                        target: wants_melee.target,
+                        damage,
+                    });
+                    if damage > 0 {
                         suffer_damage
                             .insert(wants_melee.target, SufferDamage { amount: damage })
                             .expect("Could not insert damage.");
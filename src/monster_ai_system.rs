@@ -1,35 +1,64 @@
 extern crate specs;
-use super::{Map, Monster, Point, Position, RunState, Viewshed, WantsToMelee};
+use super::{
+    scheduler, AiState, Behaviour, CombatStats, Confused, Energy, FlowFields, Map, Monster, Point,
+    Position, RandomNumberGenerator, RunState, Viewshed, WantsToMelee,
+};
 use specs::prelude::*;
 
 extern crate rltk;
+
+/// How close the player has to be, in tiles, to wake a sleeping monster.
+const WAKE_DISTANCE: f32 = 4.0;
+
+/// Monsters flee once their hp drops to this fraction of their max hp or below.
+const FLEE_HP_DIVISOR: i32 = 4;
+
+/// Moves each monster between its behaviour states, then acts on its state.
+///
+/// Sleeping monsters wake when the player comes close or they are hurt.
+/// Awake monsters chase the player while they can see them, and search where
+/// they last saw them when they can't, wandering once the trail goes cold.
+/// Badly hurt monsters flee from the player, fighting only when cornered, and
+/// confused monsters do nothing at all.
 pub struct MonsterAI {}
 
 impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        ReadExpect<'a, Map>,
+        WriteExpect<'a, Map>,
         ReadExpect<'a, Point>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
+        WriteExpect<'a, FlowFields>,
+        WriteExpect<'a, RandomNumberGenerator>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, Behaviour>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, Confused>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            map,
+            mut map,
             player_pos,
             player_entity,
             runstate,
+            mut flow_fields,
+            mut rng,
             entities,
             mut viewshed,
             monster,
             mut position,
             mut wants_to_melee,
+            mut energies,
+            mut behaviours,
+            combat_stats,
+            mut confused,
         ) = data;
 
         // Make sure AI only runs in the correct game state.
@@ -37,44 +66,181 @@ impl<'a> System<'a> for MonsterAI {
             return;
         }
 
-        for (entity, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
+        for (entity, viewshed, _monster, pos, energy, behaviour, stats) in (
+            &entities,
+            &mut viewshed,
+            &monster,
+            &mut position,
+            &mut energies,
+            &mut behaviours,
+            &combat_stats,
+        )
+            .join()
         {
+            // Only monsters with enough energy get to act this turn
+            if !scheduler::can_act(energy) {
+                continue;
+            }
+
+            // Confused monsters lose their turn
+            if let Some(confusion) = confused.get_mut(entity) {
+                confusion.turns -= 1;
+                if confusion.turns < 1 {
+                    confused.remove(entity);
+                }
+                energy.energy -= scheduler::ACTION_COST;
+                continue;
+            }
+
             let distance =
                 rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+            let can_see_player = viewshed.visible_tiles.contains(&*player_pos);
+            if can_see_player {
+                behaviour.last_seen = Some(Position {
+                    x: player_pos.x,
+                    y: player_pos.y,
+                });
+            }
+
+            behaviour.state = next_state(behaviour, stats, pos, can_see_player, distance);
 
-            // If the monster is close enough to melee, then do that.
-            if distance < 1.5 {
-                wants_to_melee
-                    .insert(
-                        entity,
-                        WantsToMelee {
-                            target: *player_entity,
-                        },
-                    )
-                    .expect("Unable to insert attack.");
-
-            // If the monster can see the player
-            } else if viewshed.visible_tiles.contains(&*player_pos) {
-                let path = rltk::a_star_search(
-                    map.xy_idx(pos.x, pos.y) as i32,
-                    map.xy_idx(player_pos.x, player_pos.y) as i32,
-                    &*map,
-                );
-
-                // If we found a way to the player and we're not right next
-                if path.success && path.steps.len() > 1 {
-                    // Move to the first position in the path
-                    let (x, y) = map.idx_to_xy(path.steps[1]);
-
-                    // Set the monsters new position
-                    pos.x = x;
-                    pos.y = y;
-
-                    // The monster has moved, recalculate it's sight.
-                    viewshed.dirty = true;
+            let step = match behaviour.state {
+                AiState::Sleeping => None,
+                AiState::Wandering => wander_step(&map, pos, *player_pos, &mut rng),
+                AiState::Chasing => {
+                    // If the monster is close enough to melee, then do that.
+                    // The attack's cost is paid when it is carried out.
+                    if distance < 1.5 {
+                        attack(&mut wants_to_melee, entity, *player_entity);
+                        continue;
+                    }
+
+                    let neighbours = open_neighbours(&map, pos, *player_pos);
+                    flow_fields
+                        .to_player
+                        .best_step(&map, map.xy_idx(pos.x, pos.y), &neighbours)
+                }
+                AiState::Searching => {
+                    let target = behaviour
+                        .last_seen
+                        .clone()
+                        .expect("Monster is searching without having seen the player.");
+                    let neighbours = open_neighbours(&map, pos, *player_pos);
+                    let step = flow_fields
+                        .towards_last_seen(&map, map.xy_idx(target.x, target.y))
+                        .best_step(&map, map.xy_idx(pos.x, pos.y), &neighbours);
+
+                    // Give up once the trail leads nowhere
+                    if step.is_none() {
+                        behaviour.state = AiState::Wandering;
+                        behaviour.last_seen = None;
+                    }
+                    step
+                }
+                AiState::Fleeing => {
+                    let neighbours = open_neighbours(&map, pos, *player_pos);
+                    let step = flow_fields.from_player.best_step(
+                        &map,
+                        map.xy_idx(pos.x, pos.y),
+                        &neighbours,
+                    );
+
+                    // Cornered, so fight back
+                    if step.is_none() && distance < 1.5 {
+                        attack(&mut wants_to_melee, entity, *player_entity);
+                        continue;
+                    }
+                    step
                 }
+            };
+
+            if let Some(idx) = step {
+                let old_idx = map.xy_idx(pos.x, pos.y);
+                energy.energy -= scheduler::move_cost(&map, old_idx, idx);
+
+                // Keep the blocked tiles up to date so that monsters moving
+                // later this turn don't walk into each other.
+                map.blocked[old_idx] = false;
+                map.blocked[idx] = true;
+
+                let (x, y) = map.idx_to_xy(idx);
+                pos.x = x;
+                pos.y = y;
+
+                // The monster has moved, recalculate it's sight.
+                viewshed.dirty = true;
+            } else {
+                // Nothing to do, so wait
+                energy.energy -= scheduler::ACTION_COST;
             }
         }
     }
 }
+
+/// Works out what a monster should be doing this turn.
+fn next_state(
+    behaviour: &Behaviour,
+    stats: &CombatStats,
+    pos: &Position,
+    can_see_player: bool,
+    distance: f32,
+) -> AiState {
+    let hurt = stats.hp < stats.max_hp;
+    let badly_hurt = stats.hp * FLEE_HP_DIVISOR <= stats.max_hp;
+    let wakes = hurt || (can_see_player && distance <= WAKE_DISTANCE);
+
+    match behaviour.state {
+        AiState::Sleeping if !wakes => AiState::Sleeping,
+        _ if can_see_player && badly_hurt => AiState::Fleeing,
+        _ if can_see_player => AiState::Chasing,
+        AiState::Chasing | AiState::Searching
+            if behaviour
+                .last_seen
+                .as_ref()
+                .is_some_and(|seen| seen.x != pos.x || seen.y != pos.y) =>
+        {
+            AiState::Searching
+        }
+        _ => AiState::Wandering,
+    }
+}
+
+fn attack(wants_to_melee: &mut WriteStorage<WantsToMelee>, entity: Entity, target: Entity) {
+    wants_to_melee
+        .insert(entity, WantsToMelee { target })
+        .expect("Unable to insert attack.");
+}
+
+/// The tiles next to a position that a monster could step onto. The player
+/// doesn't block their tile, so it is left out here.
+fn open_neighbours(map: &Map, pos: &Position, player_pos: Point) -> Vec<usize> {
+    let mut neighbours = Vec::new();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (x, y) = (pos.x + dx, pos.y + dy);
+            if (dx == 0 && dy == 0) || !map.is_in_bounds(x, y) {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if !map.blocked[idx] && (x != player_pos.x || y != player_pos.y) {
+                neighbours.push(idx);
+            }
+        }
+    }
+    neighbours
+}
+
+/// A random step, or none if the monster is boxed in.
+fn wander_step(
+    map: &Map,
+    pos: &Position,
+    player_pos: Point,
+    rng: &mut RandomNumberGenerator,
+) -> Option<usize> {
+    let neighbours = open_neighbours(map, pos, player_pos);
+    if neighbours.is_empty() {
+        return None;
+    }
+    Some(neighbours[rng.range(0, neighbours.len() as i32) as usize])
+}
@@ -0,0 +1,166 @@
+extern crate rltk;
+extern crate specs;
+use super::{
+    camera, CombatStats, Equipped, GameLog, GameStats, InBackpack, Map, MapGenConfig, Name, Player,
+    Position, RunSeed,
+};
+use specs::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MORGUE_DIR: &str = "./morgue";
+
+/// How many of the most recent log entries are included in a morgue file.
+const MORGUE_LOG_ENTRIES: usize = 20;
+
+/// Writes a plain text report of the run that has just ended to the morgue
+/// directory, returning the path it was written to.
+pub fn write_morgue(ecs: &World) -> io::Result<PathBuf> {
+    let seed = ecs.fetch::<RunSeed>().seed;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    fs::create_dir_all(MORGUE_DIR)?;
+    let path = PathBuf::from(MORGUE_DIR).join(format!("morgue-{}-{}.txt", seed, timestamp));
+    fs::write(&path, morgue_report(ecs))?;
+
+    Ok(path)
+}
+
+/// Describes the player, their belongings, what they killed, how the run
+/// ended and as much of the level as they had seen.
+pub fn morgue_report(ecs: &World) -> String {
+    let player_entity = ecs.fetch::<Entity>();
+    let game_stats = ecs.fetch::<GameStats>();
+    let log = ecs.fetch::<GameLog>();
+    let map = ecs.fetch::<Map>();
+    let seed = ecs.fetch::<RunSeed>().seed;
+    let map_config = ecs.fetch::<MapGenConfig>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let entities = ecs.entities();
+
+    let mut report: Vec<String> = Vec::new();
+
+    report.push("Rustlike morgue file".to_string());
+    report.push(format!("Seed: {}", seed));
+    report.push(format!(
+        "Levels: {}, {}x{}",
+        map_config.builder.as_deref().unwrap_or("random"),
+        map_config.width,
+        map_config.height
+    ));
+    report.push(String::new());
+    match &game_stats.last_hit_by {
+        Some(name) => report.push(format!("Slain by the {} on depth {}.", name, map.depth)),
+        None => report.push(format!("Died on depth {}.", map.depth)),
+    }
+    report.push(format!("Survived for {} turns.", game_stats.turns));
+    report.push(String::new());
+
+    report.push("== Stats ==".to_string());
+    if let Some(stats) = combat_stats.get(*player_entity) {
+        report.push(format!("HP: {} / {}", stats.hp, stats.max_hp));
+        report.push(format!("Power: {}", stats.power));
+        report.push(format!("Defence: {}", stats.defence));
+    }
+    report.push(format!("Damage dealt: {}", game_stats.damage_dealt));
+    report.push(format!("Damage taken: {}", game_stats.damage_taken));
+    report.push(format!("Potions used: {}", game_stats.potions_used));
+    report.push(String::new());
+
+    report.push("== Inventory ==".to_string());
+    let inventory: Vec<(Entity, &Name)> = (&entities, &backpack, &names)
+        .join()
+        .filter(|(_, pack, _)| pack.owner == *player_entity)
+        .map(|(item, _, name)| (item, name))
+        .collect();
+    if inventory.is_empty() {
+        report.push("Nothing".to_string());
+    }
+    for (item, name) in inventory {
+        if equipped.get(item).is_some() {
+            report.push(format!("{} (equipped)", name.name));
+        } else {
+            report.push(name.name.clone());
+        }
+    }
+    report.push(String::new());
+
+    report.push("== Kills ==".to_string());
+    let mut kills: BTreeMap<&str, i32> = BTreeMap::new();
+    for name in game_stats.kills.iter() {
+        *kills.entry(name).or_insert(0) += 1;
+    }
+    if kills.is_empty() {
+        report.push("Nothing".to_string());
+    }
+    for (name, count) in kills.iter() {
+        report.push(format!("{:>4} {}", count, name));
+    }
+    report.push(String::new());
+
+    report.push("== Achievements ==".to_string());
+    if game_stats.achievements.is_empty() {
+        report.push("None".to_string());
+    }
+    for achievement in game_stats.achievements.iter() {
+        report.push(achievement.clone());
+    }
+    report.push(String::new());
+
+    report.push("== Last messages ==".to_string());
+    let skip = log.entries.len().saturating_sub(MORGUE_LOG_ENTRIES);
+    for entry in log.entries.iter().skip(skip) {
+        report.push(entry.text());
+    }
+    report.push(String::new());
+
+    report.push(format!("== Depth {} ==", map.depth));
+    report.extend(map_dump(ecs, &map));
+
+    let mut text = report.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Draws the tiles the player has seen as text, with the player shown as `@`.
+fn map_dump(ecs: &World, map: &Map) -> Vec<String> {
+    let players = ecs.read_storage::<Player>();
+    let positions = ecs.read_storage::<Position>();
+    let player_idx = (&players, &positions)
+        .join()
+        .map(|(_, pos)| map.xy_idx(pos.x, pos.y))
+        .next();
+
+    let mut dump: Vec<String> = Vec::new();
+    for y in 0..map.height {
+        let mut line = String::new();
+        for x in 0..map.width {
+            let idx = map.xy_idx(x, y);
+            if Some(idx) == player_idx {
+                line.push('@');
+            } else if map.revealed_tiles[idx] {
+                let (glyph, _) = camera::tile_glyph(map.tiles[idx]);
+                line.push(rltk::to_char(glyph));
+            } else {
+                line.push(' ');
+            }
+        }
+        dump.push(line.trim_end().to_string());
+    }
+
+    // Leave out the unexplored rows above and below what was seen
+    while dump.last().is_some_and(|line| line.is_empty()) {
+        dump.pop();
+    }
+    let first_seen = dump.iter().position(|line| !line.is_empty()).unwrap_or(0);
+    dump.split_off(first_seen)
+}
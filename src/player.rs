@@ -1,10 +1,10 @@
 use super::{
-    CombatStats, Cursor, GameLog, Item, Map, Player, Point, Position, RunState, State, Viewshed,
-    WantsToMelee, WantsToPickupItem,
+    scheduler, CombatStats, Cursor, Energy, Equippable, Equipped, GameLog, Item, Map, Monster,
+    Player, Point, Position, Ranged, RunState, State, TileType, Viewport, Viewshed, WantsToEquip,
+    WantsToMelee, WantsToPickupItem, WantsToUnequip, WantsToUseItem,
 };
 use rltk::{Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
 pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let mut positions = ecs.write_storage::<Position>();
@@ -12,6 +12,7 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let mut ppos = ecs.write_resource::<Point>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut energies = ecs.write_storage::<Energy>();
     let entities = ecs.entities();
     let combat_stats = ecs.read_storage::<CombatStats>();
     let map = ecs.fetch::<Map>();
@@ -41,26 +42,49 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         }
 
         if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            let cost = scheduler::move_cost(&map, map.xy_idx(pos.x, pos.y), destination_idx);
+            pos.x += delta_x;
+            pos.y += delta_y;
             ppos.x = pos.x;
             ppos.y = pos.y;
 
             viewshed.dirty = true;
+            scheduler::spend_energy(&mut energies, entity, cost);
         }
     }
 }
 
 pub fn try_move_cursor(delta_x: i32, delta_y: i32, ecs: &mut World) {
+    let viewport = Viewport::for_player(ecs);
     let mut cursor = ecs.fetch_mut::<Cursor>();
     let map = ecs.fetch::<Map>();
 
-    if !map.is_in_bounds(cursor.x + delta_x, cursor.y + delta_y) {
+    // Keep the cursor on the map, and on the part of it that is on screen
+    let (x, y) = (cursor.x + delta_x, cursor.y + delta_y);
+    if !map.is_in_bounds(x, y) || viewport.world_to_screen(x, y).is_none() {
         return;
     }
 
-    cursor.x += delta_x;
-    cursor.y += delta_y;
+    cursor.x = x;
+    cursor.y = y;
+}
+
+/// Moves the cursor to the map tile that was clicked on, returning whether
+/// the click was on the map.
+fn click_cursor(ctx: &Rltk, ecs: &mut World) -> bool {
+    let viewport = Viewport::for_player(ecs);
+    let mut cursor = ecs.fetch_mut::<Cursor>();
+    let map = ecs.fetch::<Map>();
+
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    if let Some((x, y)) = viewport.screen_to_world(mouse_x, mouse_y) {
+        if map.is_in_bounds(x, y) {
+            cursor.x = x;
+            cursor.y = y;
+            return true;
+        }
+    }
+    false
 }
 
 /// Handle players input, carries out appropriate actions, and returns
@@ -71,6 +95,10 @@ pub fn try_move_cursor(delta_x: i32, delta_y: i32, ecs: &mut World) {
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
     let state = *gs.ecs.fetch::<RunState>();
 
+    if state == RunState::Examining && ctx.left_click {
+        click_cursor(ctx, &mut gs.ecs);
+    }
+
     match ctx.key {
         None => return state, // Nothing to do.
         Some(key) => match key {
@@ -92,6 +120,8 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             VirtualKeyCode::Numpad7 | VirtualKeyCode::U => handle_movement(-1, -1, &mut gs.ecs),
             VirtualKeyCode::Numpad3 | VirtualKeyCode::N => handle_movement(1, 1, &mut gs.ecs),
             VirtualKeyCode::Numpad1 | VirtualKeyCode::B => handle_movement(-1, 1, &mut gs.ecs),
+            // Wait a turn
+            VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
             // Handle Examining
             VirtualKeyCode::X => return handle_examine(&mut gs.ecs),
             // Handle Pickup
@@ -100,6 +130,18 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             VirtualKeyCode::I => return RunState::ShowInventory,
             // Handle Dropping
             VirtualKeyCode::D => return RunState::ShowDropItem,
+            // Handle Stairs
+            VirtualKeyCode::Period => return try_next_level(&mut gs.ecs),
+            VirtualKeyCode::Comma => return try_previous_level(&mut gs.ecs),
+            // Handle Message History
+            VirtualKeyCode::M => return RunState::MessageHistory { offset: 0 },
+            // Save and return to the main menu
+            VirtualKeyCode::Escape => {
+                if state == RunState::Examining {
+                    return RunState::AwaitingInput;
+                }
+                return RunState::SaveGame;
+            }
             // No key is being pressed so we're still waiting for input
             _ => return state,
         },
@@ -140,7 +182,46 @@ fn handle_movement(x: i32, y: i32, ecs: &mut World) {
     }
 }
 
-fn get_item(ecs: &mut World) -> RunState {
+/// Lets a turn pass without doing anything.
+pub fn skip_turn(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut energies = ecs.write_storage::<Energy>();
+    scheduler::spend_energy(&mut energies, player_entity, scheduler::ACTION_COST);
+    RunState::MonsterTurn
+}
+
+/// Descends to the next level if the player is standing on the down stairs.
+pub fn try_next_level(ecs: &mut World) -> RunState {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::DOWNSTAIRS {
+        RunState::NextLevel
+    } else {
+        gamelog.push("There is no way down from here.".to_string());
+        RunState::AwaitingInput
+    }
+}
+
+/// Levels are not kept once the player leaves them, so the up stairs only
+/// mark where the player arrived.
+fn try_previous_level(ecs: &mut World) -> RunState {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::UPSTAIRS {
+        gamelog.push("The way back up has collapsed behind you.".to_string());
+    } else {
+        gamelog.push("There is no way up from here.".to_string());
+    }
+    RunState::AwaitingInput
+}
+
+pub fn get_item(ecs: &mut World) -> RunState {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
     let entities = ecs.entities();
@@ -158,9 +239,7 @@ fn get_item(ecs: &mut World) -> RunState {
 
     match target_item {
         None => {
-            gamelog
-                .entries
-                .push("There is nothing here to pick up.".to_string());
+            gamelog.push("There is nothing here to pick up.".to_string());
             RunState::AwaitingInput
         }
         Some(item) => {
@@ -178,3 +257,136 @@ fn get_item(ecs: &mut World) -> RunState {
         }
     }
 }
+
+/// Uses an item from the player's backpack: gear is equipped, or taken off if
+/// it already is, ranged items need a target picking, and anything else has
+/// its effects applied to the player.
+pub fn use_item(ecs: &mut World, item: Entity) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+
+    let range = ecs.read_storage::<Ranged>().get(item).map(|r| r.range);
+    if let Some(range) = range {
+        return begin_targeting(ecs, range, item);
+    }
+
+    if ecs.read_storage::<Equipped>().get(item).is_some() {
+        ecs.write_storage::<WantsToUnequip>()
+            .insert(player_entity, WantsToUnequip { item })
+            .expect("Unable to insert intent");
+    } else if ecs.read_storage::<Equippable>().get(item).is_some() {
+        ecs.write_storage::<WantsToEquip>()
+            .insert(player_entity, WantsToEquip { item })
+            .expect("Unable to insert intent");
+    } else {
+        ecs.write_storage::<WantsToUseItem>()
+            .insert(player_entity, WantsToUseItem { item, target: None })
+            .expect("Unable to insert intent");
+    }
+    RunState::AwaitingInput
+}
+
+/// The tiles the player can see that are within range of them.
+pub fn targetable_tiles(ecs: &World, range: i32) -> Vec<Point> {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    match viewsheds.get(*player_entity) {
+        Some(viewshed) => viewshed
+            .visible_tiles
+            .iter()
+            .filter(|tile| {
+                rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, **tile) <= range as f32
+            })
+            .copied()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Starts choosing a target for a ranged item, with the cursor on the closest
+/// monster in range, or on the player if there isn't one.
+fn begin_targeting(ecs: &mut World, range: i32, item: Entity) -> RunState {
+    let tiles = targetable_tiles(ecs, range);
+    let player_pos = *ecs.fetch::<Point>();
+    let positions = ecs.read_storage::<Position>();
+    let monsters = ecs.read_storage::<Monster>();
+
+    let closest = (&positions, &monsters)
+        .join()
+        .map(|(pos, _)| Point::new(pos.x, pos.y))
+        .filter(|point| tiles.contains(point))
+        .min_by(|a, b| {
+            let distance = |p: &Point| rltk::DistanceAlg::Pythagoras.distance2d(player_pos, *p);
+            distance(a)
+                .partial_cmp(&distance(b))
+                .expect("Distance is not a number.")
+        })
+        .unwrap_or(player_pos);
+
+    let mut cursor = ecs.fetch_mut::<Cursor>();
+    cursor.x = closest.x;
+    cursor.y = closest.y;
+
+    RunState::ShowTargeting { range, item }
+}
+
+/// Handles input while aiming a ranged item. The cursor is moved with the
+/// movement keys and fired with Enter, or a tile can be clicked on to fire at
+/// it straight away. Escape puts the item away again.
+pub fn targeting_input(gs: &mut State, ctx: &mut Rltk, range: i32, item: Entity) -> RunState {
+    let state = RunState::ShowTargeting { range, item };
+
+    if ctx.left_click && click_cursor(ctx, &mut gs.ecs) {
+        return fire_at_cursor(&mut gs.ecs, range, item);
+    }
+
+    match ctx.key {
+        None => state,
+        Some(key) => {
+            let (delta_x, delta_y) = match key {
+                VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => (-1, 0),
+                VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => (1, 0),
+                VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => (0, -1),
+                VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => (0, 1),
+                VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => (1, -1),
+                VirtualKeyCode::Numpad7 | VirtualKeyCode::U => (-1, -1),
+                VirtualKeyCode::Numpad3 | VirtualKeyCode::N => (1, 1),
+                VirtualKeyCode::Numpad1 | VirtualKeyCode::B => (-1, 1),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                    return fire_at_cursor(&mut gs.ecs, range, item)
+                }
+                VirtualKeyCode::Escape => return RunState::AwaitingInput,
+                _ => return state,
+            };
+            try_move_cursor(delta_x, delta_y, &mut gs.ecs);
+            state
+        }
+    }
+}
+
+/// Uses the item on the tile under the cursor, if it is in range.
+pub fn fire_at_cursor(ecs: &mut World, range: i32, item: Entity) -> RunState {
+    let target = {
+        let cursor = ecs.fetch::<Cursor>();
+        Point::new(cursor.x, cursor.y)
+    };
+
+    if !targetable_tiles(ecs, range).contains(&target) {
+        ecs.fetch_mut::<GameLog>()
+            .push("You can't target that.".to_string());
+        return RunState::ShowTargeting { range, item };
+    }
+
+    let player_entity = *ecs.fetch::<Entity>();
+    ecs.write_storage::<WantsToUseItem>()
+        .insert(
+            player_entity,
+            WantsToUseItem {
+                item,
+                target: Some(target),
+            },
+        )
+        .expect("Unable to insert intent");
+    RunState::AwaitingInput
+}
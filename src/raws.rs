@@ -0,0 +1,426 @@
+extern crate rltk;
+use super::{EquipmentSlot, RandomTable, TileType};
+use rltk::rex::XpFile;
+use rltk::RGB;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+
+pub const RAW_FILE: &str = "./raws/spawns.json";
+
+/// The contents of the raw file, describing every monster and item that can
+/// be spawned.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    pub monsters: Vec<RawMonster>,
+    pub items: Vec<RawItem>,
+    pub spawn_table: Vec<RawSpawnEntry>,
+    pub vault_legend: Vec<RawLegendEntry>,
+    pub vaults: Vec<RawVault>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawMonster {
+    pub name: String,
+    pub renderable: RawRenderable,
+    pub blocks_tile: bool,
+    pub vision_range: i32,
+    pub stats: RawStats,
+    /// Energy gained each tick, where 100 is the player's speed.
+    pub speed: Option<i32>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawItem {
+    pub name: String,
+    pub renderable: RawRenderable,
+    /// Whether the item is used up when it is used.
+    #[serde(default)]
+    pub consumable: bool,
+    /// How far away the item can be aimed, for items used on a target.
+    pub range: Option<i32>,
+    pub effects: Option<RawEffects>,
+    pub equippable: Option<RawEquippable>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawRenderable {
+    pub glyph: String,
+    pub fg: String,
+    pub bg: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawStats {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub defence: i32,
+    pub power: i32,
+}
+
+/// What happens when an item is used. Any combination can be given.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawEffects {
+    pub provides_healing: Option<i32>,
+    pub inflicts_damage: Option<i32>,
+    pub area_of_effect: Option<i32>,
+    pub confusion: Option<i32>,
+}
+
+/// Gear that can be worn or wielded, and what it adds to its owner's stats.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawEquippable {
+    pub slot: EquipmentSlot,
+    pub power_bonus: Option<i32>,
+    pub defence_bonus: Option<i32>,
+}
+
+/// How often a monster or item is spawned, and on which depths.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawSpawnEntry {
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    pub max_depth: i32,
+}
+
+/// What a character drawn in a vault file stands for: the tile to place, and
+/// optionally a monster or item to spawn on it.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawLegendEntry {
+    pub glyph: String,
+    pub tile: TileType,
+    pub spawn: Option<String>,
+}
+
+/// A hand drawn section of map, kept in a REX Paint file.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawVault {
+    pub name: String,
+    pub file: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    pub max_depth: i32,
+}
+
+/// A vault read from its REX Paint file, with every character looked up in
+/// the legend. Cells are stored row by row; `None` leaves the map untouched.
+#[derive(Clone)]
+pub struct Vault {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    pub cells: Vec<Option<VaultCell>>,
+}
+
+#[derive(Clone)]
+pub struct VaultCell {
+    pub tile: TileType,
+    pub spawn: Option<String>,
+}
+
+/// A renderable with its glyph and colours already converted for drawing.
+#[derive(Clone)]
+pub struct SpawnRenderable {
+    pub glyph: u8,
+    pub fg: RGB,
+    pub bg: RGB,
+}
+
+/// Every entity template from the raw file, validated and ready to spawn.
+#[derive(Clone)]
+pub struct RawMaster {
+    pub raws: Raws,
+    pub vaults: Vec<Vault>,
+}
+
+impl RawMaster {
+    /// Reads and validates the raw file at `path`, reporting every problem
+    /// found rather than stopping at the first.
+    pub fn load(path: &str) -> Result<RawMaster, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read raw file {}: {}", path, e))?;
+        let raws: Raws = serde_json::from_str(&data)
+            .map_err(|e| format!("Unable to parse raw file {}: {}", path, e))?;
+
+        let mut errors = validate(&raws);
+        let vaults: Vec<Vault> = raws
+            .vaults
+            .iter()
+            .filter_map(|vault| match load_vault(vault, &raws.vault_legend) {
+                Ok(loaded) => Some(loaded),
+                Err(e) => {
+                    errors.push(format!("vault \"{}\": {}", vault.name, e));
+                    None
+                }
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(format!(
+                "Invalid raw file {}:\n  {}",
+                path,
+                errors.join("\n  ")
+            ));
+        }
+
+        Ok(RawMaster { raws, vaults })
+    }
+
+    pub fn monster(&self, name: &str) -> Option<&RawMonster> {
+        self.raws.monsters.iter().find(|m| m.name == name)
+    }
+
+    pub fn item(&self, name: &str) -> Option<&RawItem> {
+        self.raws.items.iter().find(|i| i.name == name)
+    }
+
+    /// The monsters that can spawn at the given depth, weighted by the spawn table.
+    pub fn monster_table(&self, depth: i32) -> RandomTable {
+        self.spawn_table(depth, |name| self.monster(name).is_some())
+    }
+
+    /// The items that can spawn at the given depth, weighted by the spawn table.
+    pub fn item_table(&self, depth: i32) -> RandomTable {
+        self.spawn_table(depth, |name| self.item(name).is_some())
+    }
+
+    pub fn vault(&self, name: &str) -> Option<&Vault> {
+        self.vaults.iter().find(|v| v.name == name)
+    }
+
+    /// The vaults that can be placed at the given depth, weighted by how often
+    /// they should turn up.
+    pub fn vault_table(&self, depth: i32) -> RandomTable {
+        self.raws
+            .vaults
+            .iter()
+            .filter(|v| depth >= v.min_depth && depth <= v.max_depth)
+            .fold(RandomTable::new(), |table, v| table.add(&v.name, v.weight))
+    }
+
+    fn spawn_table<F: Fn(&str) -> bool>(&self, depth: i32, include: F) -> RandomTable {
+        self.raws
+            .spawn_table
+            .iter()
+            .filter(|e| depth >= e.min_depth && depth <= e.max_depth && include(&e.name))
+            .fold(RandomTable::new(), |table, e| table.add(&e.name, e.weight))
+    }
+}
+
+impl RawRenderable {
+    /// Converts the glyph and colours; only valid once the raws have been validated.
+    pub fn to_spawn(&self) -> SpawnRenderable {
+        SpawnRenderable {
+            glyph: parse_glyph(&self.glyph).expect("Glyph was not validated"),
+            fg: RGB::from_hex(&self.fg).expect("Colour was not validated"),
+            bg: RGB::from_hex(&self.bg).expect("Colour was not validated"),
+        }
+    }
+}
+
+fn parse_glyph(glyph: &str) -> Option<u8> {
+    let mut chars = glyph.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if rltk::to_cp437(c) != 0 => Some(rltk::to_cp437(c)),
+        _ => None,
+    }
+}
+
+fn validate(raws: &Raws) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut names: HashSet<&str> = HashSet::new();
+
+    for monster in raws.monsters.iter() {
+        let context = format!("monster \"{}\"", monster.name);
+        validate_name(&monster.name, &mut names, &mut errors);
+        validate_renderable(&context, &monster.renderable, &mut errors);
+
+        if monster.vision_range < 1 {
+            errors.push(format!("{}: vision_range must be at least 1", context));
+        }
+        if monster.stats.max_hp < 1 {
+            errors.push(format!("{}: max_hp must be at least 1", context));
+        }
+        if monster.stats.hp < 1 || monster.stats.hp > monster.stats.max_hp {
+            errors.push(format!("{}: hp must be between 1 and max_hp", context));
+        }
+        if monster.stats.defence < 0 || monster.stats.power < 0 {
+            errors.push(format!("{}: defence and power can't be negative", context));
+        }
+        if monster.speed.is_some_and(|speed| speed < 1) {
+            errors.push(format!("{}: speed must be at least 1", context));
+        }
+    }
+
+    for item in raws.items.iter() {
+        let context = format!("item \"{}\"", item.name);
+        validate_name(&item.name, &mut names, &mut errors);
+        validate_renderable(&context, &item.renderable, &mut errors);
+
+        if item.range.is_some_and(|range| range < 1) {
+            errors.push(format!("{}: range must be at least 1", context));
+        }
+        if let Some(effects) = &item.effects {
+            for (field, value) in [
+                ("provides_healing", effects.provides_healing),
+                ("inflicts_damage", effects.inflicts_damage),
+                ("area_of_effect", effects.area_of_effect),
+                ("confusion", effects.confusion),
+            ] {
+                if value.is_some_and(|value| value < 1) {
+                    errors.push(format!("{}: {} must be at least 1", context, field));
+                }
+            }
+        }
+        if let Some(equippable) = &item.equippable {
+            if equippable.power_bonus.is_some_and(|bonus| bonus < 0)
+                || equippable.defence_bonus.is_some_and(|bonus| bonus < 0)
+            {
+                errors.push(format!("{}: bonuses can't be negative", context));
+            }
+        }
+    }
+
+    for entry in raws.spawn_table.iter() {
+        let context = format!("spawn table entry \"{}\"", entry.name);
+        if !names.contains(entry.name.as_str()) {
+            errors.push(format!(
+                "{}: there is no monster or item by that name",
+                context
+            ));
+        }
+        if entry.weight < 1 {
+            errors.push(format!("{}: weight must be at least 1", context));
+        }
+        if entry.min_depth > entry.max_depth {
+            errors.push(format!(
+                "{}: min_depth can't be deeper than max_depth",
+                context
+            ));
+        }
+    }
+
+    let mut glyphs: HashSet<&str> = HashSet::new();
+    for entry in raws.vault_legend.iter() {
+        let context = format!("vault legend entry \"{}\"", entry.glyph);
+        if parse_glyph(&entry.glyph).is_none() {
+            errors.push(format!(
+                "{}: glyph must be a single code page 437 character",
+                context
+            ));
+        } else if !glyphs.insert(&entry.glyph) {
+            errors.push(format!("{}: glyph is defined more than once", context));
+        }
+        if entry.tile == TileType::DOWNSTAIRS || entry.tile == TileType::UPSTAIRS {
+            errors.push(format!("{}: tile can't be stairs", context));
+        }
+        if let Some(spawn) = &entry.spawn {
+            if !names.contains(spawn.as_str()) {
+                errors.push(format!(
+                    "{}: there is no monster or item called \"{}\"",
+                    context, spawn
+                ));
+            }
+        }
+    }
+
+    let mut vault_names: HashSet<&str> = HashSet::new();
+    for vault in raws.vaults.iter() {
+        let context = format!("vault \"{}\"", vault.name);
+        if !vault_names.insert(&vault.name) {
+            errors.push(format!("{}: defined more than once", context));
+        }
+        if vault.weight < 1 {
+            errors.push(format!("{}: weight must be at least 1", context));
+        }
+        if vault.min_depth > vault.max_depth {
+            errors.push(format!(
+                "{}: min_depth can't be deeper than max_depth",
+                context
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Reads the first layer of a vault's REX Paint file, translating each
+/// character through the legend. Blank cells are left out of the vault.
+fn load_vault(vault: &RawVault, legend: &[RawLegendEntry]) -> Result<Vault, String> {
+    let mut file =
+        File::open(&vault.file).map_err(|e| format!("unable to open {}: {}", vault.file, e))?;
+    let xp =
+        XpFile::read(&mut file).map_err(|e| format!("unable to read {}: {}", vault.file, e))?;
+    let layer = xp
+        .layers
+        .first()
+        .ok_or_else(|| format!("{} has no layers", vault.file))?;
+
+    let mut cells: Vec<Option<VaultCell>> = Vec::new();
+    for y in 0..layer.height {
+        for x in 0..layer.width {
+            let ch = layer.get(x, y).map_or(0, |cell| cell.ch);
+            if ch == 0 || ch == u32::from(rltk::to_cp437(' ')) {
+                cells.push(None);
+                continue;
+            }
+
+            let glyph = rltk::to_char(ch as u8).to_string();
+            match legend.iter().find(|entry| entry.glyph == glyph) {
+                Some(entry) => cells.push(Some(VaultCell {
+                    tile: entry.tile,
+                    spawn: entry.spawn.clone(),
+                })),
+                None => {
+                    return Err(format!(
+                        "\"{}\" at ({}, {}) in {} is not in the vault legend",
+                        glyph, x, y, vault.file
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(Vault {
+        name: vault.name.clone(),
+        width: layer.width as i32,
+        height: layer.height as i32,
+        cells,
+    })
+}
+
+fn validate_name<'a>(name: &'a str, names: &mut HashSet<&'a str>, errors: &mut Vec<String>) {
+    if name.trim().is_empty() {
+        errors.push("every monster and item needs a name".to_string());
+    } else if !names.insert(name) {
+        errors.push(format!("\"{}\" is defined more than once", name));
+    }
+}
+
+fn validate_renderable(context: &str, renderable: &RawRenderable, errors: &mut Vec<String>) {
+    if parse_glyph(&renderable.glyph).is_none() {
+        errors.push(format!(
+            "{}: glyph \"{}\" must be a single code page 437 character",
+            context, renderable.glyph
+        ));
+    }
+    for colour in [&renderable.fg, &renderable.bg].iter() {
+        if RGB::from_hex(colour).is_err() {
+            errors.push(format!(
+                "{}: colour \"{}\" must be in the form #RRGGBB",
+                context, colour
+            ));
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use rand::distributions::uniform::SampleUniform;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+/// The run's random number generator. It rolls exactly as rltk's does, but
+/// its state is saved with the game, so that a reloaded run carries on with
+/// the rolls its seed would have given it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RandomNumberGenerator {
+    rng: XorShiftRng,
+}
+
+impl RandomNumberGenerator {
+    pub fn seeded(seed: u64) -> RandomNumberGenerator {
+        RandomNumberGenerator {
+            rng: XorShiftRng::seed_from_u64(seed),
+        }
+    }
+
+    /// A random value from `min` up to but not including `max`.
+    pub fn range<T: SampleUniform>(&mut self, min: T, max: T) -> T {
+        self.rng.gen_range(min, max)
+    }
+
+    /// Rolls `n` dice with `die_type` sides each and adds them up.
+    pub fn roll_dice(&mut self, n: i32, die_type: i32) -> i32 {
+        (0..n).map(|_| self.range(1, die_type + 1)).sum()
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
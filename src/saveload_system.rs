@@ -0,0 +1,432 @@
+extern crate specs;
+use super::{
+    components::*, gamelog::GameLog, gamestats::GameStats, map::Map, map_builders::MapGenConfig,
+    map_builders::MapGenHistory, register_components, Cursor, EventQueue, FlowFields,
+    HighScoreTable, Player, Point, Position, RandomNumberGenerator, RawMaster, RunState, SoundHook,
+};
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+const SAVE_FILE: &str = "./savegame.json";
+
+/// Bumped whenever the layout of the save file changes, so that old saves
+/// are rejected rather than loaded into the wrong components.
+const SAVE_VERSION: u32 = 12;
+
+/// Written at the start of every save file, ahead of the component data.
+#[derive(Serialize, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )?;
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<NoError, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &$data.0,
+            &mut $data.1,
+            &mut $data.2,
+            &mut $de,
+        )?;
+        )*
+    };
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+/// Writes every marked entity, along with the world's resources, to the save file.
+pub fn save_game(ecs: &mut World) -> io::Result<()> {
+    save_to(ecs, Path::new(SAVE_FILE))
+}
+
+fn save_to(ecs: &mut World, path: &Path) -> io::Result<()> {
+    // Resources can't be serialized directly, so carry them on a helper entity.
+    let helper = SerializationHelper {
+        map: (*ecs.fetch::<Map>()).clone(),
+        log: (*ecs.fetch::<GameLog>()).clone(),
+        cursor: (*ecs.fetch::<Cursor>()).clone(),
+        seed: *ecs.fetch::<RunSeed>(),
+        rng: (*ecs.fetch::<RandomNumberGenerator>()).clone(),
+        stats: (*ecs.fetch::<GameStats>()).clone(),
+    };
+    let savehelper = ecs
+        .create_entity()
+        .with(helper)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    let result = write_save(ecs, path);
+
+    ecs.delete_entity(savehelper)
+        .expect("Unable to delete save helper");
+
+    result
+}
+
+fn write_save(ecs: &World, path: &Path) -> io::Result<()> {
+    let data = (
+        ecs.entities(),
+        ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+    );
+
+    let writer = File::create(path)?;
+    let mut serializer = serde_json::Serializer::new(writer);
+    SaveHeader {
+        version: SAVE_VERSION,
+    }
+    .serialize(&mut serializer)?;
+    serialize_individually!(
+        ecs,
+        serializer,
+        data,
+        Position,
+        Renderable,
+        Player,
+        Viewshed,
+        Monster,
+        Name,
+        BlocksTile,
+        CombatStats,
+        Energy,
+        Behaviour,
+        SufferDamage,
+        WantsToMelee,
+        Item,
+        Consumable,
+        Ranged,
+        ProvidesHealing,
+        InflictsDamage,
+        AreaOfEffect,
+        Confusion,
+        Confused,
+        InBackpack,
+        WantsToPickupItem,
+        WantsToUseItem,
+        WantsToDropItem,
+        Equippable,
+        Equipped,
+        MeleePowerBonus,
+        DefenceBonus,
+        WantsToEquip,
+        WantsToUnequip,
+        SerializationHelper
+    );
+
+    serializer.into_inner().flush()
+}
+
+/// Replaces the contents of the world with the contents of the save file.
+/// The world is left as it was if the save can't be loaded.
+pub fn load_game(ecs: &mut World) -> io::Result<()> {
+    load_from(ecs, Path::new(SAVE_FILE))
+}
+
+fn load_from(ecs: &mut World, path: &Path) -> io::Result<()> {
+    let data = fs::read_to_string(path)?;
+    let mut de = serde_json::Deserializer::from_str(&data);
+
+    let header = SaveHeader::deserialize(&mut de)?;
+    if header.version != SAVE_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Save file version {} is not supported (expected {})",
+                header.version, SAVE_VERSION
+            ),
+        ));
+    }
+
+    // Restore into an empty world, so that nothing is lost if the save
+    // turns out to be incomplete.
+    let mut loaded = World::new();
+    register_components(&mut loaded);
+    loaded.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+    {
+        let mut d = (
+            &mut loaded.entities(),
+            &mut loaded.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut loaded.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            loaded,
+            de,
+            d,
+            Position,
+            Renderable,
+            Player,
+            Viewshed,
+            Monster,
+            Name,
+            BlocksTile,
+            CombatStats,
+            Energy,
+            Behaviour,
+            SufferDamage,
+            WantsToMelee,
+            Item,
+            Consumable,
+            Ranged,
+            ProvidesHealing,
+            InflictsDamage,
+            AreaOfEffect,
+            Confusion,
+            Confused,
+            InBackpack,
+            WantsToPickupItem,
+            WantsToUseItem,
+            WantsToDropItem,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenceBonus,
+            WantsToEquip,
+            WantsToUnequip,
+            SerializationHelper
+        );
+    }
+    de.end()?;
+
+    restore_resources(&mut loaded)?;
+    carry_over_resources(ecs, &mut loaded);
+    *ecs = loaded;
+
+    Ok(())
+}
+
+/// Moves the resources off the helper entity and into the loaded world, and
+/// points the player resources at the restored player.
+fn restore_resources(ecs: &mut World) -> io::Result<()> {
+    let helper = {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+        (&entities, &helpers)
+            .join()
+            .map(|(entity, h)| (entity, h.clone()))
+            .next()
+    };
+    let (helper_entity, helper) =
+        helper.ok_or_else(|| invalid_save("Save file is missing the game's resources"))?;
+
+    let player = {
+        let entities = ecs.entities();
+        let players = ecs.read_storage::<Player>();
+        let positions = ecs.read_storage::<Position>();
+        (&entities, &players, &positions)
+            .join()
+            .map(|(entity, _player, pos)| (entity, Point::new(pos.x, pos.y)))
+            .next()
+    };
+    let (player_entity, player_pos) =
+        player.ok_or_else(|| invalid_save("Save file is missing the player"))?;
+
+    let mut map = helper.map;
+    map.tile_content = vec![Vec::new(); map.tiles.len()];
+    ecs.insert(map);
+    ecs.insert(helper.log);
+    ecs.insert(helper.cursor);
+    ecs.insert(helper.seed);
+    ecs.insert(helper.rng);
+    ecs.insert(helper.stats);
+    ecs.insert(player_pos);
+    ecs.insert(player_entity);
+
+    ecs.delete_entity(helper_entity)
+        .expect("Unable to delete save helper");
+
+    Ok(())
+}
+
+/// Hands the resources that aren't saved over to the loaded world, and gives
+/// it fresh copies of the ones that only last for a turn.
+fn carry_over_resources(from: &mut World, to: &mut World) {
+    to.insert(from.remove::<RawMaster>().expect("No raws to carry over"));
+    to.insert(
+        from.remove::<MapGenConfig>()
+            .expect("No map settings to carry over"),
+    );
+    to.insert(
+        from.remove::<HighScoreTable>()
+            .expect("No high scores to carry over"),
+    );
+    to.insert(
+        from.remove::<SoundHook>()
+            .expect("No sound hook to carry over"),
+    );
+    to.insert(MapGenHistory::default());
+    to.insert(EventQueue::default());
+    to.insert(FlowFields::default());
+    to.insert(RunState::PreRun);
+}
+
+fn invalid_save(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn delete_save() -> io::Result<()> {
+    if does_save_exist() {
+        fs::remove_file(SAVE_FILE)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_game, InBackpack, Name, MAPHEIGHT, MAPWIDTH, RAW_FILE};
+    use std::path::PathBuf;
+
+    fn test_world(seed: u64) -> World {
+        let raws = RawMaster::load(RAW_FILE).expect("Unable to load raws");
+        let config = MapGenConfig {
+            builder: None,
+            show_generation: false,
+            width: MAPWIDTH,
+            height: MAPHEIGHT,
+        };
+        new_game(seed, &raws, &config)
+    }
+
+    /// A save file of its own for each test, so that tests can run side by
+    /// side without touching the player's save.
+    fn save_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rustlike-{}-{}.json", name, std::process::id()))
+    }
+
+    /// The name and position of every named entity, in a stable order.
+    fn named_entities(ecs: &World) -> Vec<(String, Option<(i32, i32)>)> {
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let mut named: Vec<(String, Option<(i32, i32)>)> = (&ecs.entities(), &names)
+            .join()
+            .map(|(entity, name)| {
+                let pos = positions.get(entity).map(|pos| (pos.x, pos.y));
+                (name.name.clone(), pos)
+            })
+            .collect();
+        named.sort();
+        named
+    }
+
+    fn player_hp(ecs: &World) -> (i32, i32) {
+        let stats = ecs.read_storage::<CombatStats>();
+        let player_stats = stats
+            .get(*ecs.fetch::<Entity>())
+            .expect("Player has no stats");
+        (player_stats.hp, player_stats.max_hp)
+    }
+
+    fn log_text(ecs: &World) -> Vec<String> {
+        ecs.fetch::<GameLog>()
+            .entries
+            .iter()
+            .map(|entry| entry.text())
+            .collect()
+    }
+
+    #[test]
+    fn saved_world_loads_into_a_fresh_world() {
+        let mut saved = test_world(1);
+        {
+            let player = *saved.fetch::<Entity>();
+            saved
+                .write_storage::<CombatStats>()
+                .get_mut(player)
+                .expect("Player has no stats")
+                .hp -= 5;
+            saved.fetch_mut::<GameLog>().push("Something happened.");
+            saved.fetch_mut::<GameStats>().turns = 42;
+        }
+        let item = {
+            let items = saved.read_storage::<Item>();
+            (&saved.entities(), &items)
+                .join()
+                .map(|(entity, _item)| entity)
+                .next()
+                .expect("No items were spawned")
+        };
+        let player = *saved.fetch::<Entity>();
+        saved.write_storage::<Position>().remove(item);
+        saved
+            .write_storage::<InBackpack>()
+            .insert(item, InBackpack { owner: player })
+            .expect("Unable to pack item");
+
+        let path = save_path("round-trip");
+        save_to(&mut saved, &path).expect("Unable to save");
+        let mut loaded = test_world(2);
+        let result = load_from(&mut loaded, &path);
+        fs::remove_file(&path).expect("Unable to remove save");
+        result.expect("Unable to load");
+
+        let player = *loaded.fetch::<Entity>();
+        assert!(loaded.read_storage::<Player>().get(player).is_some());
+        assert_eq!(player_hp(&loaded), player_hp(&saved));
+        let (saved_pos, loaded_pos) = (*saved.fetch::<Point>(), *loaded.fetch::<Point>());
+        assert_eq!((loaded_pos.x, loaded_pos.y), (saved_pos.x, saved_pos.y));
+
+        let (saved_map, loaded_map) = (saved.fetch::<Map>(), loaded.fetch::<Map>());
+        assert_eq!(loaded_map.depth, saved_map.depth);
+        assert!(loaded_map.tiles == saved_map.tiles);
+        assert_eq!(loaded_map.revealed_tiles, saved_map.revealed_tiles);
+
+        assert_eq!(log_text(&loaded), log_text(&saved));
+        assert_eq!(loaded.fetch::<GameStats>().turns, 42);
+        assert_eq!(loaded.fetch::<RunSeed>().seed, 1);
+        assert_eq!(named_entities(&loaded), named_entities(&saved));
+
+        // The loaded run goes on to roll what the saved one would have
+        let mut saved_rng = saved.fetch_mut::<RandomNumberGenerator>();
+        let mut loaded_rng = loaded.fetch_mut::<RandomNumberGenerator>();
+        for _ in 0..10 {
+            assert_eq!(loaded_rng.next_u64(), saved_rng.next_u64());
+        }
+
+        let packs = loaded.read_storage::<InBackpack>();
+        let carried: Vec<&InBackpack> = packs.join().collect();
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].owner, player);
+    }
+
+    #[test]
+    fn failed_load_leaves_world_untouched() {
+        let mut world = test_world(1);
+        let before = named_entities(&world);
+
+        let path = save_path("truncated");
+        save_to(&mut world, &path).expect("Unable to save");
+        let data = fs::read_to_string(&path).expect("Unable to read save");
+        fs::write(&path, &data[..data.len() / 2]).expect("Unable to truncate save");
+
+        let result = load_from(&mut world, &path);
+        fs::remove_file(&path).expect("Unable to remove save");
+
+        assert!(result.is_err());
+        assert_eq!(named_entities(&world), before);
+        let player = *world.fetch::<Entity>();
+        assert!(world.read_storage::<Player>().get(player).is_some());
+    }
+}
@@ -0,0 +1,81 @@
+extern crate specs;
+use super::{Energy, GameStats, Map, Monster, RunState};
+use specs::prelude::*;
+
+/// The energy a normal speed entity gains each tick of game time.
+pub const NORMAL_SPEED: i32 = 100;
+
+/// Ticks of game time in a turn, which is how long a normal speed entity
+/// takes to make an ordinary action.
+pub const TICKS_PER_TURN: i32 = 10;
+
+/// The energy an ordinary action, such as moving, attacking or waiting,
+/// costs. An entity can act whenever it has at least this much energy.
+pub const ACTION_COST: i32 = NORMAL_SPEED * TICKS_PER_TURN;
+
+pub const PICKUP_COST: i32 = ACTION_COST * 3 / 2;
+pub const DROP_COST: i32 = ACTION_COST / 2;
+pub const USE_ITEM_COST: i32 = ACTION_COST;
+pub const EQUIP_COST: i32 = ACTION_COST;
+
+/// The energy it costs to step from a tile onto a neighbouring one, in
+/// proportion to what paths charge for the step, so diagonal steps and rough
+/// ground take longer than an ordinary action.
+pub fn move_cost(map: &Map, from_idx: usize, to_idx: usize) -> i32 {
+    (ACTION_COST as f32 * map.step_cost(from_idx, to_idx)) as i32
+}
+
+pub fn can_act(energy: &Energy) -> bool {
+    energy.energy >= ACTION_COST
+}
+
+/// Takes the cost of an action from an entity's energy.
+pub fn spend_energy(energies: &mut WriteStorage<Energy>, entity: Entity, cost: i32) {
+    if let Some(energy) = energies.get_mut(entity) {
+        energy.energy -= cost;
+    }
+}
+
+/// Whether the player has the energy to act. A player without an energy
+/// component can always act.
+pub fn player_can_act(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let energies = ecs.read_storage::<Energy>();
+    energies.get(*player_entity).is_none_or(can_act)
+}
+
+/// Decides who acts next, moving game time forward until someone can.
+/// Monsters that are ready act before the player, and each monster acts at
+/// most once per monster turn, so a fast monster may get several turns in a
+/// row.
+pub fn next_turn(ecs: &mut World) -> RunState {
+    loop {
+        {
+            let monsters = ecs.read_storage::<Monster>();
+            let energies = ecs.read_storage::<Energy>();
+            if (&monsters, &energies)
+                .join()
+                .any(|(_, energy)| can_act(energy))
+            {
+                return RunState::MonsterTurn;
+            }
+        }
+        if player_can_act(ecs) {
+            return RunState::AwaitingInput;
+        }
+
+        advance_time(ecs);
+    }
+}
+
+/// Moves game time on by a tick, giving every entity its speed in energy.
+fn advance_time(ecs: &mut World) {
+    let mut energies = ecs.write_storage::<Energy>();
+    for energy in (&mut energies).join() {
+        energy.energy += energy.speed;
+    }
+
+    let mut game_stats = ecs.write_resource::<GameStats>();
+    game_stats.ticks += 1;
+    game_stats.turns = game_stats.ticks / TICKS_PER_TURN;
+}
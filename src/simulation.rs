@@ -0,0 +1,322 @@
+extern crate rltk;
+extern crate specs;
+use super::{
+    fire_at_cursor, get_item, is_player_dead, new_game, skip_turn, try_move_player, try_next_level,
+    use_item, AreaOfEffect, CombatStats, Cursor, Equippable, Equipped, FlowField, GameStats,
+    InBackpack, Item, Map, MapGenConfig, Monster, Point, Position, ProvidesHealing, Ranged,
+    RawMaster, RunState, State, TileType, Viewshed, UNREACHABLE,
+};
+use rltk::BaseMap;
+use specs::prelude::*;
+
+/// An action the player can take without going through the keyboard.
+pub enum PlayerAction {
+    Move {
+        delta_x: i32,
+        delta_y: i32,
+    },
+    PickUp,
+    /// Uses an item from the backpack, as if it was picked from the inventory.
+    UseItem(Entity),
+    /// Aims a ranged item from the backpack at a point.
+    UseItemAt(Entity, Point),
+    Descend,
+    Wait,
+}
+
+/// Decides what the player does each turn of a headless run. Scripted runs
+/// and bots both implement this.
+pub trait PlayerController {
+    fn next_action(&mut self, ecs: &World) -> PlayerAction;
+}
+
+/// A simple bot that drinks potions when hurt, equips gear for empty slots,
+/// reads scrolls at monsters, fights anything it can see, picks up items,
+/// and otherwise heads for the stairs.
+pub struct Bot {}
+
+/// The outcome of a single headless run.
+pub struct SimulationResult {
+    pub seed: u64,
+    pub depth: i32,
+    pub died: bool,
+    pub stats: GameStats,
+}
+
+/// Plays `runs` games with the bot, seeded from `seed` upwards, and prints a
+/// report of the outcomes.
+pub fn run_simulations(
+    runs: u32,
+    seed: u64,
+    max_turns: i32,
+    raws: &RawMaster,
+    map_config: &MapGenConfig,
+) {
+    let results: Vec<SimulationResult> = (0..u64::from(runs))
+        .map(|i| run_simulation(seed + i, max_turns, raws, map_config, &mut Bot {}))
+        .collect();
+
+    println!(
+        "{:>20} {:>6} {:>6} {:>6} {:>7} {:>7} {:>8} {:>8}",
+        "seed", "depth", "turns", "kills", "dealt", "taken", "potions", "outcome"
+    );
+    for result in results.iter() {
+        println!(
+            "{:>20} {:>6} {:>6} {:>6} {:>7} {:>7} {:>8} {:>8}",
+            result.seed,
+            result.depth,
+            result.stats.turns,
+            result.stats.kills.len(),
+            result.stats.damage_dealt,
+            result.stats.damage_taken,
+            result.stats.potions_used,
+            if result.died { "died" } else { "survived" }
+        );
+    }
+
+    if results.is_empty() {
+        return;
+    }
+
+    let count = results.len() as f32;
+    let average = |f: &dyn Fn(&SimulationResult) -> i32| -> f32 {
+        results.iter().map(|r| f(r) as f32).sum::<f32>() / count
+    };
+    println!(
+        "{:>20} {:>6.1} {:>6.1} {:>6.1} {:>7.1} {:>7.1} {:>8.1} {:>7.0}%",
+        "average",
+        average(&|r| r.depth),
+        average(&|r| r.stats.turns),
+        average(&|r| r.stats.kills.len() as i32),
+        average(&|r| r.stats.damage_dealt),
+        average(&|r| r.stats.damage_taken),
+        average(&|r| r.stats.potions_used),
+        average(&|r| if r.died { 100 } else { 0 })
+    );
+}
+
+/// Plays a single game without a console, using the same system pipeline as
+/// the interactive game, until the player dies or `max_turns` have passed.
+pub fn run_simulation(
+    seed: u64,
+    max_turns: i32,
+    raws: &RawMaster,
+    map_config: &MapGenConfig,
+    controller: &mut dyn PlayerController,
+) -> SimulationResult {
+    let mut gs = State {
+        ecs: new_game(seed, raws, map_config),
+        next_seed: None,
+        // Headless runs aren't recorded in the high scores or the morgue
+        run_ended: true,
+    };
+
+    // Guards against a controller that never ends its turn.
+    let max_steps = max_turns * 10;
+    let mut steps = 0;
+
+    loop {
+        let runstate = *gs.ecs.fetch::<RunState>();
+        let next_runstate = gs.play_turn(runstate, |gs| {
+            let action = controller.next_action(&gs.ecs);
+            apply_action(gs, action)
+        });
+        gs.finish_tick(next_runstate);
+
+        steps += 1;
+        let died = is_player_dead(&gs.ecs);
+        if died || gs.ecs.fetch::<GameStats>().turns >= max_turns || steps >= max_steps {
+            return SimulationResult {
+                seed,
+                depth: gs.ecs.fetch::<Map>().depth,
+                died,
+                stats: (*gs.ecs.fetch::<GameStats>()).clone(),
+            };
+        }
+    }
+}
+
+/// Carries out an action the same way the matching key press would.
+fn apply_action(gs: &mut State, action: PlayerAction) -> RunState {
+    match action {
+        PlayerAction::Move { delta_x, delta_y } => {
+            try_move_player(delta_x, delta_y, &mut gs.ecs);
+            RunState::MonsterTurn
+        }
+        PlayerAction::PickUp => get_item(&mut gs.ecs),
+        PlayerAction::UseItem(item) => use_item(&mut gs.ecs, item),
+        PlayerAction::UseItemAt(item, target) => match use_item(&mut gs.ecs, item) {
+            RunState::ShowTargeting { range, item } => {
+                {
+                    let mut cursor = gs.ecs.fetch_mut::<Cursor>();
+                    cursor.x = target.x;
+                    cursor.y = target.y;
+                }
+                fire_at_cursor(&mut gs.ecs, range, item)
+            }
+            runstate => runstate,
+        },
+        PlayerAction::Descend => try_next_level(&mut gs.ecs),
+        PlayerAction::Wait => skip_turn(&mut gs.ecs),
+    }
+}
+
+impl PlayerController for Bot {
+    fn next_action(&mut self, ecs: &World) -> PlayerAction {
+        let player_entity = ecs.fetch::<Entity>();
+        let player_pos = ecs.fetch::<Point>();
+        let map = ecs.fetch::<Map>();
+        let entities = ecs.entities();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let viewsheds = ecs.read_storage::<Viewshed>();
+        let positions = ecs.read_storage::<Position>();
+        let monsters = ecs.read_storage::<Monster>();
+        let items = ecs.read_storage::<Item>();
+        let potions = ecs.read_storage::<ProvidesHealing>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let equippable = ecs.read_storage::<Equippable>();
+        let equipped = ecs.read_storage::<Equipped>();
+        let ranged = ecs.read_storage::<Ranged>();
+        let area_of_effect = ecs.read_storage::<AreaOfEffect>();
+
+        // Drink a potion if we're badly hurt
+        if let Some(stats) = combat_stats.get(*player_entity) {
+            if stats.hp < stats.max_hp / 2 {
+                let potion = (&entities, &backpack, &potions)
+                    .join()
+                    .find(|(_, pack, _)| pack.owner == *player_entity);
+                if let Some((potion, _, _)) = potion {
+                    return PlayerAction::UseItem(potion);
+                }
+            }
+        }
+
+        // Put on any gear that fills an empty slot
+        let gear = (&entities, &backpack, &equippable)
+            .join()
+            .filter(|(_, pack, _)| pack.owner == *player_entity)
+            .find(|(_, _, gear)| {
+                !equipped
+                    .join()
+                    .any(|worn| worn.owner == *player_entity && worn.slot == gear.slot)
+            });
+        if let Some((gear, _, _)) = gear {
+            return PlayerAction::UseItem(gear);
+        }
+
+        let visible = match viewsheds.get(*player_entity) {
+            Some(viewshed) => &viewshed.visible_tiles,
+            None => return PlayerAction::Wait,
+        };
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        let from_player = FlowField::new(&map, &[player_idx]);
+        let cost_to = |point: Point| {
+            let cost = from_player.values[map.xy_idx(point.x, point.y)];
+            (cost != UNREACHABLE).then_some(cost)
+        };
+
+        // Fight the closest monster we can see
+        let target = (&positions, &monsters)
+            .join()
+            .map(|(pos, _)| Point::new(pos.x, pos.y))
+            .filter(|point| visible.contains(point))
+            .filter_map(|point| cost_to(point).map(|cost| (cost, point)))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((_, target)) = target {
+            // Read a scroll at it if one reaches without catching us too
+            let scroll = (&entities, &backpack, &ranged)
+                .join()
+                .filter(|(_, pack, _)| pack.owner == *player_entity)
+                .find(|(scroll, _, ranged)| {
+                    let range = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, target);
+                    range <= ranged.range as f32
+                        && area_of_effect
+                            .get(*scroll)
+                            .is_none_or(|area| range > area.radius as f32)
+                });
+            if let Some((scroll, _, _)) = scroll {
+                return PlayerAction::UseItemAt(scroll, target);
+            }
+
+            if rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, target) < 1.5 {
+                return PlayerAction::Move {
+                    delta_x: target.x - player_pos.x,
+                    delta_y: target.y - player_pos.y,
+                };
+            }
+            return step_towards(&map, player_idx, target);
+        }
+
+        // Collect any items we can see
+        let item = (&positions, &items)
+            .join()
+            .map(|(pos, _)| Point::new(pos.x, pos.y))
+            .filter(|point| visible.contains(point))
+            .filter_map(|point| cost_to(point).map(|cost| (cost, point)))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((_, item)) = item {
+            if item == *player_pos {
+                return PlayerAction::PickUp;
+            }
+            return step_towards(&map, player_idx, item);
+        }
+
+        // Otherwise head down
+        if map.tiles[player_idx] == TileType::DOWNSTAIRS {
+            return PlayerAction::Descend;
+        }
+        match map.tiles.iter().position(|t| *t == TileType::DOWNSTAIRS) {
+            Some(stairs_idx) => {
+                let (x, y) = map.idx_to_xy(stairs_idx);
+                step_towards(&map, player_idx, Point::new(x, y))
+            }
+            None => PlayerAction::Wait,
+        }
+    }
+}
+
+/// Takes the first step along the cheapest path to a point, going around
+/// anything standing in the way.
+fn step_towards(map: &Map, from_idx: usize, to: Point) -> PlayerAction {
+    let field = FlowField::new(map, &[map.xy_idx(to.x, to.y)]);
+    let candidates: Vec<usize> = map
+        .get_available_exits(from_idx)
+        .iter()
+        .map(|(idx, _)| *idx)
+        .collect();
+
+    match field.best_step(map, from_idx, &candidates) {
+        Some(step_idx) => {
+            let (x, y) = map.idx_to_xy(step_idx);
+            let (from_x, from_y) = map.idx_to_xy(from_idx);
+            PlayerAction::Move {
+                delta_x: x - from_x,
+                delta_y: y - from_y,
+            }
+        }
+        None => PlayerAction::Wait,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RAW_FILE;
+
+    #[test]
+    fn bot_plays_a_seeded_run() {
+        let raws = RawMaster::load(RAW_FILE).expect("Unable to load raws");
+        let config = MapGenConfig::default();
+
+        let first = run_simulation(1, 50, &raws, &config, &mut Bot {});
+        assert!(first.stats.turns > 0);
+        assert!(first.died || first.stats.turns >= 50);
+
+        // The same seed plays out the same way
+        let second = run_simulation(1, 50, &raws, &config, &mut Bot {});
+        assert_eq!(second.stats.turns, first.stats.turns);
+        assert_eq!(second.stats.kills, first.stats.kills);
+        assert_eq!(second.stats.damage_taken, first.stats.damage_taken);
+        assert_eq!(second.depth, first.depth);
+    }
+}
@@ -0,0 +1,92 @@
+extern crate specs;
+use super::{EventQueue, GameEvent};
+use specs::prelude::*;
+
+/// The sounds the game asks for. Nothing is played yet; an audio backend
+/// only needs to install a `SoundHook` that plays these.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SoundCue {
+    Hit,
+    Miss,
+    Death,
+    PickUp,
+    Drop,
+    Heal,
+    Equip,
+}
+
+/// Called with every sound cue as it happens.
+pub struct SoundHook {
+    pub play: fn(SoundCue),
+}
+
+impl Default for SoundHook {
+    fn default() -> SoundHook {
+        SoundHook { play: |_| {} }
+    }
+}
+
+/// Turns events into sound cues for the sound hook.
+pub struct SoundSystem {}
+
+impl<'a> System<'a> for SoundSystem {
+    type SystemData = (ReadExpect<'a, EventQueue>, ReadExpect<'a, SoundHook>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (queue, hook) = data;
+
+        for event in queue.events.iter() {
+            let cue = match event {
+                GameEvent::Attacked { damage: 0, .. } => SoundCue::Miss,
+                GameEvent::Attacked { .. } | GameEvent::ItemHit { .. } => SoundCue::Hit,
+                GameEvent::Died { .. } => SoundCue::Death,
+                GameEvent::ItemPickedUp { .. } => SoundCue::PickUp,
+                GameEvent::ItemDropped { .. } => SoundCue::Drop,
+                GameEvent::Healed { .. } => SoundCue::Heal,
+                GameEvent::ItemEquipped { .. } | GameEvent::ItemUnequipped { .. } => {
+                    SoundCue::Equip
+                }
+                GameEvent::Damaged { .. } | GameEvent::Confused { .. } => continue,
+            };
+            (hook.play)(cue);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static PLAYED: RefCell<Vec<SoundCue>> = const { RefCell::new(Vec::new()) };
+    }
+
+    #[test]
+    fn events_play_their_cues() {
+        let mut world = World::new();
+        let player = world.create_entity().build();
+        let monster = world.create_entity().build();
+        world.insert(SoundHook {
+            play: |cue| PLAYED.with(|played| played.borrow_mut().push(cue)),
+        });
+        world.insert(EventQueue {
+            events: vec![
+                GameEvent::Attacked {
+                    attacker: player,
+                    target: monster,
+                    damage: 0,
+                },
+                GameEvent::Damaged {
+                    target: monster,
+                    amount: 4,
+                },
+                GameEvent::Died { entity: monster },
+            ],
+        });
+
+        SoundSystem {}.run_now(&world);
+
+        PLAYED.with(|played| assert_eq!(*played.borrow(), vec![SoundCue::Miss, SoundCue::Death]));
+    }
+}
@@ -1,11 +1,14 @@
 extern crate rltk;
 extern crate specs;
 use super::{
-    BlocksTile, CombatStats, Item, Monster, Name, Player, Position, Potion, Rect, Renderable,
-    Viewshed, MAPWIDTH,
+    scheduler, AiState, AreaOfEffect, Behaviour, BlocksTile, CombatStats, Confusion, Consumable,
+    DefenceBonus, Energy, Equippable, InflictsDamage, Item, Map, MeleePowerBonus, Monster, Name,
+    Player, Position, ProvidesHealing, RandomNumberGenerator, Ranged, RawItem, RawMaster,
+    RawMonster, Renderable, SerializeMe, Viewshed,
 };
-use rltk::{RandomNumberGenerator, RGB};
+use rltk::RGB;
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
@@ -36,118 +39,187 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             defence: 1,
             power: 4,
         })
+        // The player starts ready to act
+        .with(Energy {
+            speed: scheduler::NORMAL_SPEED,
+            energy: scheduler::ACTION_COST,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
-pub fn populate_room(ecs: &mut World, room: &Rect) {
-    let mut monster_spawn_points: Vec<usize> = Vec::new();
-    let mut item_spawn_points: Vec<usize> = Vec::new();
+/// Fills an area of the map, given as a list of tile indices, with monsters
+/// and items picked from the spawn tables for the given depth. Deeper areas
+/// hold more monsters.
+pub fn spawn_region(ecs: &mut World, map: &Map, area: &[usize], depth: i32) {
+    let mut spawns: Vec<(usize, String)> = Vec::new();
 
     {
+        let raws = ecs.fetch::<RawMaster>();
+        let monster_table = raws.monster_table(depth);
+        let item_table = raws.item_table(depth);
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) - 3;
-        let num_items = rng.roll_dice(1, MAX_ITEMS + 2) - 3;
+        let mut free_tiles: Vec<usize> = area.to_vec();
+
+        // Never try to fit more spawns into an area than it has tiles
+        let max_spawns = (area.len() / 2) as i32;
+        let num_monsters = i32::min(
+            rng.roll_dice(1, MAX_MONSTERS + 2) + (depth - 1) - 3,
+            max_spawns,
+        );
+        let num_items = i32::min(
+            rng.roll_dice(1, MAX_ITEMS + 2) + (depth - 1) / 2 - 3,
+            max_spawns - i32::max(0, num_monsters),
+        );
 
         for _i in 0..num_monsters {
-            // Loop until a valid point to spawn is found
-            loop {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !monster_spawn_points.contains(&idx) {
-                    monster_spawn_points.push(idx);
-                    // Valid spawn found, break out of loop
-                    break;
-                }
+            let idx = take_spawn_point(&mut rng, &mut free_tiles);
+            if let Some(name) = monster_table.roll(&mut rng) {
+                spawns.push((idx, name));
             }
         }
 
         for _i in 0..num_items {
-            // Loop until a valid point to spawn is found
-            loop {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !item_spawn_points.contains(&idx) {
-                    item_spawn_points.push(idx);
-                    // Valid spawn found, break out of loop
-                    break;
-                }
+            let idx = take_spawn_point(&mut rng, &mut free_tiles);
+            if let Some(name) = item_table.roll(&mut rng) {
+                spawns.push((idx, name));
             }
         }
     }
 
-    for idx in monster_spawn_points.iter() {
-        let x = (*idx % MAPWIDTH) as i32;
-        let y = (*idx / MAPWIDTH) as i32;
-        random_monster(ecs, x, y);
-    }
-    for idx in monster_spawn_points.iter() {
-        let x = (*idx % MAPWIDTH) as i32;
-        let y = (*idx / MAPWIDTH) as i32;
-        health_potion(ecs, x, y);
+    for (idx, name) in spawns.iter() {
+        let (x, y) = map.idx_to_xy(*idx);
+        spawn_named(ecs, name, x, y);
     }
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('i'),
-            fg: RGB::named(rltk::MAGENTA),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Name {
-            name: "Health Potion".to_string(),
-        })
-        .with(Item {})
-        .with(Potion { heal_amount: 8 })
-        .build();
+/// Removes a random tile from those still free to spawn on.
+fn take_spawn_point(rng: &mut RandomNumberGenerator, free_tiles: &mut Vec<usize>) -> usize {
+    let i = (rng.roll_dice(1, free_tiles.len() as i32) - 1) as usize;
+    free_tiles.remove(i)
 }
 
-pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
-    let roll: i32;
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+/// Spawns the monster or item with the given name from the raw files.
+pub fn spawn_named(ecs: &mut World, name: &str, x: i32, y: i32) {
+    let monster = ecs.fetch::<RawMaster>().monster(name).cloned();
+    if let Some(monster) = monster {
+        spawn_monster(ecs, &monster, x, y);
+        return;
     }
-    match roll {
-        1 => orc(ecs, x, y),
-        _ => goblin(ecs, x, y),
+
+    let item = ecs.fetch::<RawMaster>().item(name).cloned();
+    if let Some(item) = item {
+        spawn_item(ecs, &item, x, y);
+        return;
     }
-}
 
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+    rltk::console::log(format!(
+        "Unable to spawn {}, it isn't in the raw files",
+        name
+    ));
 }
 
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
-}
+fn spawn_monster(ecs: &mut World, monster: &RawMonster, x: i32, y: i32) {
+    // Half of all monsters start out asleep
+    let state = if ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 2)
+        == 1
+    {
+        AiState::Sleeping
+    } else {
+        AiState::Wandering
+    };
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
-    ecs.create_entity()
+    let render = monster.renderable.to_spawn();
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
+            glyph: render.glyph,
+            fg: render.fg,
+            bg: render.bg,
         })
-        .with(BlocksTile {})
         .with(Viewshed {
             visible_tiles: Vec::new(),
-            range: 8,
+            range: monster.vision_range,
             dirty: true,
         })
         .with(Monster {})
+        .with(Behaviour {
+            state,
+            last_seen: None,
+        })
         .with(Name {
-            name: name.to_string(),
+            name: monster.name.to_string(),
         })
         .with(CombatStats {
-            max_hp: 9,
-            hp: 9,
-            defence: 1,
-            power: 4,
+            max_hp: monster.stats.max_hp,
+            hp: monster.stats.hp,
+            defence: monster.stats.defence,
+            power: monster.stats.power,
         })
-        .build();
+        .with(Energy {
+            speed: monster.speed.unwrap_or(scheduler::NORMAL_SPEED),
+            energy: 0,
+        });
+
+    if monster.blocks_tile {
+        builder = builder.with(BlocksTile {});
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
+}
+
+fn spawn_item(ecs: &mut World, item: &RawItem, x: i32, y: i32) {
+    let render = item.renderable.to_spawn();
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: render.glyph,
+            fg: render.fg,
+            bg: render.bg,
+        })
+        .with(Name {
+            name: item.name.to_string(),
+        })
+        .with(Item {});
+
+    if item.consumable {
+        builder = builder.with(Consumable {});
+    }
+
+    if let Some(range) = item.range {
+        builder = builder.with(Ranged { range });
+    }
+
+    if let Some(effects) = &item.effects {
+        if let Some(heal_amount) = effects.provides_healing {
+            builder = builder.with(ProvidesHealing { heal_amount });
+        }
+        if let Some(damage) = effects.inflicts_damage {
+            builder = builder.with(InflictsDamage { damage });
+        }
+        if let Some(radius) = effects.area_of_effect {
+            builder = builder.with(AreaOfEffect { radius });
+        }
+        if let Some(turns) = effects.confusion {
+            builder = builder.with(Confusion { turns });
+        }
+    }
+
+    if let Some(equippable) = &item.equippable {
+        builder = builder.with(Equippable {
+            slot: equippable.slot,
+        });
+        if let Some(power) = equippable.power_bonus {
+            builder = builder.with(MeleePowerBonus { power });
+        }
+        if let Some(defence) = equippable.defence_bonus {
+            builder = builder.with(DefenceBonus { defence });
+        }
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }